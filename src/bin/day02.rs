@@ -1,48 +1,116 @@
 use std::{
-    array,
+    fs,
     io::{self, BufRead},
+    str::FromStr,
 };
 
 use anyhow::{bail, ensure, Context, Ok, Result};
+use aoc2023::log;
+use itertools::Itertools;
 
-fn parse_line(line: &str) -> Result<impl Iterator<Item = Result<[u32; 3]>> + '_> {
-    let sets = line.split_once(": ").context("invalid input line")?.1;
-    let sets = sets.split("; ").map(|set| {
-        set.split(", ")
+/// The colors and how many cubes of each are in the bag.
+#[derive(Debug, Clone)]
+struct Bag {
+    colors: Vec<String>,
+    limits: Vec<u32>,
+}
+
+impl Default for Bag {
+    fn default() -> Self {
+        Self {
+            colors: ["red", "green", "blue"].map(String::from).to_vec(),
+            limits: vec![12, 13, 14],
+        }
+    }
+}
+
+/// Parses `color=count` entries separated by commas or newlines.
+impl FromStr for Bag {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (colors, limits): (Vec<_>, Vec<_>) = s
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
             .map(|entry| {
-                let (count, color) = entry.split_once(' ').context("invalid set entry")?;
-                let count = count.parse()?;
-                let color = match color {
-                    "red" => 0,
-                    "green" => 1,
-                    "blue" => 2,
-                    _ => bail!("invalid color {color:?}"),
-                };
-                Ok((count, color))
-            })
-            .try_fold([0; 3], |mut counts, entry| {
-                let (count, color) = entry?;
-                ensure!(counts[color] == 0, "duplicate color {color:?}");
-                counts[color] = count;
-                Ok(counts)
+                let (color, limit) = entry.split_once('=').context("invalid bag entry")?;
+                let limit: u32 = limit.trim().parse().context("invalid cube count")?;
+                Ok((color.trim().to_owned(), limit))
             })
-    });
-    Ok(sets)
+            .process_results(|entries| entries.unzip())?;
+        ensure!(!colors.is_empty(), "empty bag");
+        ensure!(colors.iter().all_unique(), "duplicate color in bag");
+        Ok(Self { colors, limits })
+    }
+}
+
+impl Bag {
+    fn parse_line<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Result<impl Iterator<Item = Result<Vec<u32>>> + 'a> {
+        let sets = line.split_once(": ").context("invalid input line")?.1;
+        let sets = sets.split("; ").map(|set| {
+            set.split(", ")
+                .map(|entry| {
+                    let (count, color) = entry.split_once(' ').context("invalid set entry")?;
+                    let count = count.parse()?;
+                    let Some(color) = self.colors.iter().position(|c| c == color) else {
+                        bail!("invalid color {color:?}");
+                    };
+                    Ok((count, color))
+                })
+                .try_fold(vec![0; self.colors.len()], |mut counts, entry| {
+                    let (count, color) = entry?;
+                    ensure!(counts[color] == 0, "duplicate color {color:?}");
+                    counts[color] = count;
+                    Ok(counts)
+                })
+        });
+        Ok(sets)
+    }
+}
+
+fn parse_bag() -> Result<Bag> {
+    let args = log::args().collect_vec();
+    match args.iter().map(String::as_str).collect_vec()[..] {
+        [] => Ok(Bag::default()),
+        ["--bag", spec] => spec.parse(),
+        ["--config", path] => fs::read_to_string(path)
+            .with_context(|| format!("failed to read {path}"))?
+            .parse(),
+        _ => bail!("usage: day02 [--bag COLOR=COUNT,... | --config FILE]"),
+    }
 }
 
 fn main() -> Result<()> {
+    let bag = parse_bag()?;
     let stdin = io::stdin().lock();
     let mut part1 = 0;
-    let mut part2 = 0;
+    let mut part2 = 0_u64;
     for (i, line) in stdin.lines().enumerate() {
-        let max = parse_line(&line?)?.try_fold([0; 3], |max, set| {
-            let set = set?;
-            Ok(array::from_fn(|i| max[i].max(set[i])))
-        })?;
-        if max[0] <= 12 && max[1] <= 13 && max[2] <= 14 {
+        let line = line?;
+        let max = bag
+            .parse_line(&line)?
+            .try_fold(vec![0; bag.colors.len()], |mut max, set| {
+                for (max, count) in max.iter_mut().zip(set?) {
+                    *max = count.max(*max);
+                }
+                Ok(max)
+            })?;
+        if max
+            .iter()
+            .zip(&bag.limits)
+            .all(|(count, limit)| count <= limit)
+        {
             part1 += i + 1;
         }
-        part2 += max[0] * max[1] * max[2];
+        part2 = max
+            .iter()
+            .try_fold(1_u64, |power, &count| power.checked_mul(count.into()))
+            .and_then(|power| part2.checked_add(power))
+            .context("power overflows")?;
     }
 
     println!("Part 1: {part1}");
@@ -1,38 +1,112 @@
 use std::io::{self, BufRead};
 
-use anyhow::{Context, Result};
-use rustc_hash::{FxHashMap, FxHashSet};
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::log;
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+
+#[derive(Debug, Clone, Default)]
+struct Card {
+    matches: usize,
+    points: usize,
+    /// Total number of instances, including the original.
+    instances: usize,
+    /// The earlier cards that won copies of this one, with the number of copies each.
+    won_from: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Options {
+    breakdown: bool,
+    strict: bool,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self::default();
+        for flag in log::args() {
+            match flag.as_str() {
+                "--breakdown" => options.breakdown = true,
+                "--strict" => options.strict = true,
+                _ => bail!("usage: day04 [--breakdown] [--strict]"),
+            }
+        }
+
+        Ok(options)
+    }
+}
 
 fn main() -> Result<()> {
-    let stdin = io::stdin().lock();
-    let mut counts = FxHashMap::<usize, usize>::default();
-    let mut part1 = 0;
-    let mut part2 = 0;
-    for (i, line) in stdin.lines().enumerate() {
-        let line = line?;
-        let count = counts.get(&i).copied().unwrap_or(1);
-        let (winning, chosen) = line
-            .split_once(": ")
-            .context("invalid input")?
-            .1
-            .split_once(" | ")
-            .context("invalid input")?;
-        let winning: FxHashSet<_> = winning.split_ascii_whitespace().collect();
-        let num_correct = chosen
-            .split_ascii_whitespace()
-            .filter(|num| winning.contains(num))
-            .count();
-
-        if let Some(exponent) = num_correct.checked_sub(1) {
-            part1 += 1 << exponent;
+    let options = Options::from_args()?;
+    let mut cards = io::stdin()
+        .lock()
+        .lines()
+        .map(|line| {
+            let line = line?;
+            let (winning, chosen) = line
+                .split_once(": ")
+                .context("invalid input")?
+                .1
+                .split_once(" | ")
+                .context("invalid input")?;
+            let winning: FxHashSet<_> = winning.split_ascii_whitespace().collect();
+            let matches = chosen
+                .split_ascii_whitespace()
+                .filter(|num| winning.contains(num))
+                .count();
+            let points = matches.checked_sub(1).map_or(0, |exponent| 1 << exponent);
+            Ok(Card {
+                matches,
+                points,
+                instances: 1,
+                won_from: Vec::new(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let num_cards = cards.len();
+    for i in 0..num_cards {
+        let (matches, instances) = (cards[i].matches, cards[i].instances);
+        if i + matches >= num_cards {
+            let message = format!(
+                "card {} awards copies of cards {}..={} past the end of the table ({} cards)",
+                i + 1,
+                (i + 2).max(num_cards + 1),
+                i + 1 + matches,
+                num_cards
+            );
+            ensure!(!options.strict, "{}", message);
+            eprintln!("warning: {message}");
         }
 
-        part2 += count;
-        for off in 1..=num_correct {
-            *counts.entry(i + off).or_insert(1) += count;
+        for card in cards.iter_mut().skip(i + 1).take(matches) {
+            card.instances += instances;
+            card.won_from.push((i, instances));
         }
     }
 
+    if options.breakdown {
+        for (i, card) in cards.iter().enumerate() {
+            let won_from = card
+                .won_from
+                .iter()
+                .map(|&(j, copies)| format!("{} ({copies})", j + 1))
+                .join(", ");
+            println!(
+                "Card {}: {} matches, {} points, {} copies won{}{}",
+                i + 1,
+                card.matches,
+                card.points,
+                card.instances - 1,
+                if won_from.is_empty() { "" } else { " from " },
+                won_from
+            );
+        }
+    }
+
+    let part1: usize = cards.iter().map(|card| card.points).sum();
+    let part2: usize = cards.iter().map(|card| card.instances).sum();
+
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
     Ok(())
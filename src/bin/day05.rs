@@ -4,10 +4,91 @@ use std::{
     str::FromStr,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::{log, log::Level, piecewise_map::PiecewiseMap};
 use itertools::Itertools;
 
+#[derive(Debug, Clone, Default)]
+struct Options {
+    explain: bool,
+    reverse: Option<Range<u64>>,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            if flag == "--explain" {
+                options.explain = true;
+                continue;
+            }
+
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--reverse" => {
+                    let (start, end) = value.split_once("..").context("invalid range")?;
+                    let range = start.parse()?..end.parse()?;
+                    ensure!(!range.is_empty(), "empty range: {}", value);
+                    options.reverse = Some(range);
+                }
+                _ => bail!("usage: day05 [-v...] [--explain] [--reverse START..END]"),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Sorts `ranges` and merges overlapping or adjacent ones.
+fn merge_ranges(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.sort_unstable_by_key(|range| range.start);
+    let mut merged: Vec<Range<u64>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+fn intersect_ranges(ranges: &[Range<u64>], ranges2: &[Range<u64>]) -> Vec<Range<u64>> {
+    merge_ranges(
+        ranges
+            .iter()
+            .cartesian_product(ranges2)
+            .map(|(range, range2)| range.start.max(range2.start)..range.end.min(range2.end))
+            .filter(|range| !range.is_empty())
+            .collect(),
+    )
+}
+
+fn format_ranges(ranges: &[Range<u64>]) -> String {
+    ranges
+        .iter()
+        .map(|range| format!("{}..{}", range.start, range.end))
+        .join(", ")
+}
+
+/// Lists the value of `seed` in each category.
+fn trace(names: &[String], stages: &[PiecewiseMap], seed: u64) -> String {
+    let values = stages.iter().scan(seed, |value, stage| {
+        *value = stage.get(*value);
+        Some(*value)
+    });
+    names
+        .iter()
+        .zip([seed].into_iter().chain(values))
+        .map(|(name, value)| format!("{name} {value}"))
+        .join(", ")
+}
+
 fn main() -> Result<()> {
+    let options = Options::from_args()?;
     let mut lines = io::stdin().lock().lines().fuse();
     let seeds: Vec<_> = lines
         .next()
@@ -20,10 +101,21 @@ fn main() -> Result<()> {
         .collect::<Result<_, _>>()?;
     lines.next();
 
-    let mut mappings = Vec::new();
-    while lines.next().is_some() {
+    let mut names = vec!["seed".to_owned()];
+    let mut stages = Vec::new();
+    while let Some(header) = lines.next() {
+        let header = header?;
+        if header.is_empty() {
+            continue;
+        }
+        let (_, name) = header
+            .strip_suffix(" map:")
+            .and_then(|header| header.split_once("-to-"))
+            .context("invalid map header")?;
+        names.push(name.to_owned());
+
         let mut ranges = Vec::new();
-        while let Some(line) = lines.next() {
+        for line in lines.by_ref() {
             let line = line?;
             if line.is_empty() {
                 break;
@@ -37,70 +129,83 @@ fn main() -> Result<()> {
             ranges.push((src_start?, dest_start?, width?));
         }
 
-        ranges.sort_unstable_by_key(|(src_start, _, _)| *src_start);
-        let mut low = 0;
-        let mut ranges: Vec<_> = ranges
-            .into_iter()
-            .flat_map(|(src_start, dest_start, width)| {
-                let range1 = (low < src_start).then_some((low, low));
-                let range2 = (src_start, dest_start);
-                low = src_start + width;
-                [range1, Some(range2)]
-            })
-            .flatten()
-            .collect();
-        ranges.push((low, low));
-        ranges.push((u64::MAX, u64::MAX));
-
-        mappings.push(ranges);
+        stages.push(PiecewiseMap::from_ranges(ranges)?);
+    }
+    let almanac = stages
+        .iter()
+        .fold(PiecewiseMap::default(), |almanac, stage| {
+            almanac.compose(stage)
+        });
+    log!(
+        Info,
+        "{} maps composed into {} pieces",
+        stages.len(),
+        almanac.pieces().count()
+    );
+    for (src, dest_start) in almanac.pieces() {
+        log!(Trace, "{}..{} -> {dest_start}", src.start, src.end);
     }
 
-    let part1 = seeds
+    let part1_seed = seeds
         .iter()
         .copied()
-        .map(|mut seed| {
-            for mapping in &mappings {
-                let index = mapping.partition_point(|(src_start, _)| *src_start <= seed) - 1;
-                let (src_start, dest_start) = mapping[index];
-                seed = seed - src_start + dest_start;
-            }
-
-            seed
-        })
-        .min()
+        .min_by_key(|&seed| almanac.get(seed))
         .context("empty input")?;
+    let part1 = almanac.get(part1_seed);
 
-    let mut ranges: Vec<_> = seeds
-        .iter()
-        .copied()
-        .tuples()
-        .map(|(start, width)| start..(start + width))
-        .collect();
-    ranges.sort_unstable_by_key(|range| range.start);
-    for mapping in &mappings {
-        ranges = ranges
-            .into_iter()
-            .flat_map(|range| {
-                let Range { start, end } = range;
-                mapping.iter().copied().tuple_windows().flat_map(
-                    move |((src_start, dest_start), (src_end, _))| {
-                        let low = start.max(src_start);
-                        let high = end.min(src_end);
-                        (low < high).then(|| {
-                            (low - src_start + dest_start)..(high - src_start + dest_start)
-                        })
-                    },
-                )
-            })
-            .collect();
+    let seed_ranges = merge_ranges(
+        seeds
+            .iter()
+            .copied()
+            .tuples()
+            .map(|(start, width)| start..(start + width))
+            .collect(),
+    );
+    if log::enabled(Level::Debug) {
+        let mut ranges = seed_ranges.clone();
+        for (name, stage) in names[1..].iter().zip(&stages) {
+            ranges = merge_ranges(
+                ranges
+                    .into_iter()
+                    .flat_map(|range| stage.image(range).collect_vec())
+                    .collect(),
+            );
+            log!(Debug, "{name}: {}", format_ranges(&ranges));
+        }
     }
-    dbg!(&ranges);
-    let part2 = ranges
-        .into_iter()
+    let part2 = seed_ranges
+        .iter()
+        .flat_map(|range| almanac.image(range.clone()))
         .map(|range| range.start)
         .min()
         .context("empty input")?;
 
+    if options.explain {
+        let part2_seed = intersect_ranges(&almanac.preimage(part2..part2 + 1), &seed_ranges)
+            .first()
+            .context("no seed for minimum location")?
+            .start;
+        println!("Part 1 minimum: {}", trace(&names, &stages, part1_seed));
+        println!("Part 2 minimum: {}", trace(&names, &stages, part2_seed));
+    }
+    if let Some(range) = options.reverse {
+        let mut ranges = vec![range];
+        for (name, stage) in names[1..].iter().zip(&stages).rev() {
+            println!("{name}: {}", format_ranges(&ranges));
+            ranges = merge_ranges(
+                ranges
+                    .into_iter()
+                    .flat_map(|range| stage.preimage(range))
+                    .collect(),
+            );
+        }
+        println!("{}: {}", names[0], format_ranges(&ranges));
+        println!(
+            "Within seed ranges: {}",
+            format_ranges(&intersect_ranges(&ranges, &seed_ranges))
+        );
+    }
+
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
     Ok(())
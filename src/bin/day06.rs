@@ -1,54 +1,78 @@
 use std::io::{self, BufRead};
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use itertools::Itertools;
+use num_bigint::BigUint;
+use num_integer::{Integer, Roots};
 
-fn read_line(line: &str) -> Result<Vec<u64>> {
-    line.split_once(":")
+fn read_line(line: &str) -> Result<Vec<String>> {
+    let numbers = line
+        .split_once(':')
         .context("invalid input")?
         .1
         .split_ascii_whitespace()
-        .map(|s| Ok(s.parse()?))
-        .collect()
+        .map(str::to_owned)
+        .collect_vec();
+    for number in &numbers {
+        ensure!(
+            number.bytes().all(|c| c.is_ascii_digit()),
+            "invalid number: {}",
+            number
+        );
+    }
+    Ok(numbers)
 }
 
-fn binary_search(mut low: u64, mut high: u64, mut pred: impl FnMut(u64) -> bool) -> u64 {
-    while high - low > 1 {
-        let mid = low + (high - low) / 2;
-        if pred(mid) {
-            high = mid;
-        } else {
-            low = mid;
-        }
+/// Counts the `x` with `x * (time - x) > distance`.
+///
+/// That is `(2x - time)^2 < time^2 - 4 distance`, so `|2x - time|` must be at most the integer
+/// square root of the right side minus one, and has the same parity as `time`.
+fn count_ways_to_beat<T: Integer + Roots + Clone + From<u8>>(time: T, distance: T) -> T {
+    let square = time.clone() * time.clone();
+    let four_distance = distance * T::from(4);
+    if four_distance >= square {
+        return T::zero();
     }
 
-    low
+    let max_offset = (square - four_distance - T::one()).sqrt();
+    if max_offset.is_odd() == time.is_odd() {
+        max_offset + T::one()
+    } else {
+        max_offset
+    }
 }
 
-fn count_ways_to_beat(time: u64, distance: u64) -> u64 {
-    let mid = time / 2;
-    if mid * (time - mid) <= distance {
-        return 0;
+/// Solves a race using `u128` if `time^2` and `4 distance` fit, and big integers otherwise.
+fn count_ways_to_beat_any(time: &str, distance: &str) -> Result<BigUint> {
+    let small = time
+        .parse::<u128>()
+        .ok()
+        .zip(distance.parse::<u128>().ok())
+        .filter(|&(time, distance)| {
+            time.checked_mul(time).is_some() && distance.checked_mul(4).is_some()
+        });
+    if let Some((time, distance)) = small {
+        return Ok(count_ways_to_beat(time, distance).into());
     }
 
-    let low = binary_search(0, mid, |x| x * (time - x) > distance);
-    let high = binary_search(mid, time + 1, |x| x * (time - x) <= distance);
-    high - low
+    let time: BigUint = time.parse().context("invalid time")?;
+    let distance: BigUint = distance.parse().context("invalid distance")?;
+    Ok(count_ways_to_beat(time, distance))
 }
 
 fn main() -> Result<()> {
     let mut lines = io::stdin().lock().lines();
     let times = read_line(&lines.next().context("invalid input")??)?;
     let distances = read_line(&lines.next().context("invalid input")??)?;
-    let part1: u64 = times
+    ensure!(times.len() == distances.len(), "mismatched number of races");
+
+    let part1 = times
         .iter()
         .zip(&distances)
-        .map(|(&time, &distance)| count_ways_to_beat(time, distance))
-        .product();
+        .map(|(time, distance)| count_ways_to_beat_any(time, distance))
+        .fold_ok(BigUint::from(1_u8), |product, ways| product * ways)?;
 
-    let joined_time = times.iter().join("").parse().unwrap();
-    let joined_distance = distances.iter().join("").parse().unwrap();
-    let part2 = count_ways_to_beat(joined_time, joined_distance);
+    let part2 = count_ways_to_beat_any(&times.concat(), &distances.concat())?;
 
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
@@ -1,113 +1,407 @@
 use std::{
-    array,
-    cmp::{Ordering, Reverse},
+    fmt::{self, Display},
     io::{self, BufRead},
-    mem,
+    str::FromStr,
 };
 
-use anyhow::{Context, Ok, Result};
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::log;
 use itertools::Itertools;
 
-const CARDS: &[u8] = b"23456789TJQKA";
-const NUM_CARDS: usize = CARDS.len();
-const JOKER: u8 = 9;
+/// The card counts of a hand after assigning wilds, sorted in decreasing order.
+///
+/// Comparing these lexicographically gives the usual ordering of hand types, for any hand size.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct HandType(Vec<usize>);
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPairs,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+impl Display for HandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.0[..] {
+            [5] => "Five of a kind",
+            [4, 1] => "Four of a kind",
+            [3, 2] => "Full house",
+            [3, 1, 1] => "Three of a kind",
+            [2, 2, 1] => "Two pairs",
+            [2, 1, 1, 1] => "One pair",
+            [1, 1, 1, 1, 1] => "High card",
+            _ => return write!(f, "{}", self.0.iter().join("+")),
+        };
+        f.write_str(name)
+    }
 }
 
-impl HandType {
-    fn detect(hand: [u8; 5], use_jokers: bool) -> Self {
-        let mut card_counts = hand
-            .iter()
-            .copied()
-            .fold([0; NUM_CARDS], |mut counts, card| {
-                counts[card as usize] += 1;
-                counts
-            });
-        let joker_count = if use_jokers {
-            mem::replace(&mut card_counts[JOKER as usize], 0)
-        } else {
-            0
+/// Where wild cards rank when breaking ties between hands of the same type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+enum WildRank {
+    #[default]
+    Lowest,
+    Natural,
+    Highest,
+}
+
+impl FromStr for WildRank {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lowest" => Ok(Self::Lowest),
+            "natural" => Ok(Self::Natural),
+            "highest" => Ok(Self::Highest),
+            _ => bail!("invalid wild rank: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    /// The cards from weakest to strongest.
+    deck: Vec<u8>,
+    hand_size: usize,
+    wilds: Vec<bool>,
+    wild_rank: WildRank,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            deck: b"23456789TJQKA".to_vec(),
+            hand_size: 5,
+            wilds: vec![false; 13],
+            wild_rank: WildRank::Lowest,
+        }
+    }
+}
+
+impl Rules {
+    fn with_wilds(&self, wilds: &[u8]) -> Result<Self> {
+        let mut rules = Self {
+            wilds: vec![false; self.deck.len()],
+            ..self.clone()
         };
-        let counts: Vec<_> = card_counts
+        for &card in wilds {
+            rules.wilds[self.card_index(card)?] = true;
+        }
+        Ok(rules)
+    }
+
+    fn card_index(&self, card: u8) -> Result<usize> {
+        self.deck
             .iter()
-            .copied()
-            .sorted_by_key(|c| Reverse(*c))
+            .position(|&c| c == card)
+            .with_context(|| format!("invalid card: {}", char::from(card)))
+    }
+
+    fn parse_hand(&self, hand: &str) -> Result<Vec<usize>> {
+        ensure!(
+            hand.len() == self.hand_size,
+            "hand {} does not have {} cards",
+            hand,
+            self.hand_size
+        );
+        hand.bytes().map(|card| self.card_index(card)).collect()
+    }
+
+    fn hand_type(&self, hand: &[usize]) -> HandType {
+        let mut card_counts = vec![0; self.deck.len()];
+        let mut wild_count = 0;
+        for &card in hand {
+            if self.wilds[card] {
+                wild_count += 1;
+            } else {
+                card_counts[card] += 1;
+            }
+        }
+
+        let mut counts = card_counts
+            .into_iter()
             .filter(|&c| c > 0)
-            .collect();
-
-        if joker_count == 5 || counts[0] + joker_count == 5 {
-            Self::FiveOfAKind
-        } else if counts[0] + joker_count == 4 {
-            Self::FourOfAKind
-        } else if counts[0] <= 3 && counts[1] <= 2 && counts[0] + counts[1] + joker_count == 5 {
-            Self::FullHouse
-        } else if counts[0] + joker_count == 3 {
-            Self::ThreeOfAKind
-        } else if counts[0] + counts[1] + joker_count == 4 {
-            Self::TwoPairs
-        } else if counts[0] + joker_count == 2 {
-            Self::OnePair
-        } else {
-            Self::HighCard
+            .sorted_unstable_by(|a, b| b.cmp(a))
+            .collect_vec();
+        match counts.first_mut() {
+            Some(count) => *count += wild_count,
+            None => counts.push(wild_count),
+        }
+        HandType(counts)
+    }
+
+    /// The key comparing hands of the same type card by card.
+    fn tie_break(&self, hand: &[usize]) -> Vec<(u8, usize)> {
+        hand.iter()
+            .map(|&card| {
+                let group = match (self.wilds[card], self.wild_rank) {
+                    (true, WildRank::Lowest) => 0,
+                    (true, WildRank::Highest) => 2,
+                    _ => 1,
+                };
+                (group, card)
+            })
+            .collect()
+    }
+
+    /// The hand with each wild replaced by the card giving its type: the most frequent other card,
+    /// preferring stronger ones, or the strongest non-wild card if all cards are wild.
+    fn substitute(&self, hand: &[usize]) -> Vec<usize> {
+        let mut card_counts = vec![0; self.deck.len()];
+        for &card in hand.iter().filter(|&&card| !self.wilds[card]) {
+            card_counts[card] += 1;
+        }
+        let target = (0..self.deck.len())
+            .filter(|&card| !self.wilds[card])
+            .max_by_key(|&card| (card_counts[card], card));
+        hand.iter()
+            .map(|&card| match target {
+                Some(target) if self.wilds[card] => target,
+                _ => card,
+            })
+            .collect()
+    }
+
+    fn format_hand(&self, hand: &[usize]) -> String {
+        hand.iter()
+            .map(|&card| char::from(self.deck[card]))
+            .collect()
+    }
+
+    /// The rank of each hand, starting at 1 for the weakest.
+    fn ranks(&self, hands: &[(Vec<usize>, usize)]) -> Vec<usize> {
+        let mut ranks = vec![0; hands.len()];
+        let order = hands
+            .iter()
+            .enumerate()
+            .map(|(i, (hand, _))| (self.hand_type(hand), self.tie_break(hand), i))
+            .sorted_unstable()
+            .map(|(_, _, i)| i);
+        for (i, rank) in order.zip(1..) {
+            ranks[i] = rank;
+        }
+        ranks
+    }
+
+    fn total_winnings(&self, hands: &[(Vec<usize>, usize)]) -> usize {
+        self.ranks(hands)
+            .into_iter()
+            .zip(hands)
+            .map(|(rank, (_, bid))| rank * bid)
+            .sum()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Report {
+    Table,
+    Csv,
+}
+
+impl FromStr for Report {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            _ => bail!("invalid report format: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+enum SortKey {
+    #[default]
+    Input,
+    Hand,
+    Bid,
+    Rank,
+    WildRank,
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "input" => Ok(Self::Input),
+            "hand" => Ok(Self::Hand),
+            "bid" => Ok(Self::Bid),
+            "rank" => Ok(Self::Rank),
+            "wild-rank" => Ok(Self::WildRank),
+            _ => bail!("invalid sort key: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ReportRow {
+    hand: String,
+    bid: usize,
+    hand_type: HandType,
+    wild_type: HandType,
+    substitution: String,
+    rank: usize,
+    wild_rank: usize,
+}
+
+fn report_rows(
+    rules: &Rules,
+    wild_rules: &Rules,
+    hands: &[(Vec<usize>, usize)],
+    sort: SortKey,
+) -> Vec<ReportRow> {
+    let ranks = rules.ranks(hands);
+    let wild_ranks = wild_rules.ranks(hands);
+    let mut rows = hands
+        .iter()
+        .zip(ranks.into_iter().zip(wild_ranks))
+        .map(|((hand, bid), (rank, wild_rank))| ReportRow {
+            hand: rules.format_hand(hand),
+            bid: *bid,
+            hand_type: rules.hand_type(hand),
+            wild_type: wild_rules.hand_type(hand),
+            substitution: rules.format_hand(&wild_rules.substitute(hand)),
+            rank,
+            wild_rank,
+        })
+        .collect_vec();
+    match sort {
+        SortKey::Input => {}
+        SortKey::Hand => rows.sort_by(|a, b| a.hand.cmp(&b.hand)),
+        SortKey::Bid => rows.sort_by_key(|row| row.bid),
+        SortKey::Rank => rows.sort_by_key(|row| row.rank),
+        SortKey::WildRank => rows.sort_by_key(|row| row.wild_rank),
+    }
+    rows
+}
+
+fn print_report(rows: &[ReportRow], report: Report) {
+    match report {
+        Report::Table => println!(
+            "{:<8} {:>6} {:<16} {:<16} {:<8} {:>6} {:>10} {:>9} {:>13}",
+            "hand",
+            "bid",
+            "type",
+            "wild type",
+            "as",
+            "rank",
+            "winnings",
+            "wild rank",
+            "wild winnings"
+        ),
+        Report::Csv => {
+            println!("hand,bid,type,wild_type,substitution,rank,winnings,wild_rank,wild_winnings")
+        }
+    }
+    for row in rows {
+        let winnings = row.rank * row.bid;
+        let wild_winnings = row.wild_rank * row.bid;
+        match report {
+            Report::Table => println!(
+                "{:<8} {:>6} {:<16} {:<16} {:<8} {:>6} {:>10} {:>9} {:>13}",
+                row.hand,
+                row.bid,
+                row.hand_type.to_string(),
+                row.wild_type.to_string(),
+                row.substitution,
+                row.rank,
+                winnings,
+                row.wild_rank,
+                wild_winnings
+            ),
+            Report::Csv => println!(
+                "{},{},{},{},{},{},{winnings},{},{wild_winnings}",
+                row.hand,
+                row.bid,
+                row.hand_type,
+                row.wild_type,
+                row.substitution,
+                row.rank,
+                row.wild_rank
+            ),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+struct Options {
+    rules: Rules,
+    wilds: Vec<u8>,
+    report: Option<Report>,
+    sort: SortKey,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut rules = Rules::default();
+        let mut wilds = None;
+        let mut report = None;
+        let mut sort = SortKey::default();
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--deck" => {
+                    ensure!(value.bytes().all_unique(), "duplicate card in deck");
+                    rules.deck = value.into_bytes();
+                    rules.wilds = vec![false; rules.deck.len()];
+                }
+                "--hand-size" => rules.hand_size = value.parse().context("invalid hand size")?,
+                "--wilds" => wilds = Some(value.into_bytes()),
+                "--wild-rank" => rules.wild_rank = value.parse()?,
+                "--report" => report = Some(value.parse()?),
+                "--sort" => sort = value.parse()?,
+                _ => bail!(
+                    "usage: day07 [--deck CARDS] [--hand-size N] [--wilds CARDS] \
+                     [--wild-rank lowest|natural|highest] [--report table|csv] \
+                     [--sort input|hand|bid|rank|wild-rank]"
+                ),
+            }
+        }
+
+        ensure!(rules.hand_size > 0, "hands must not be empty");
+        // Jokers are only wild by default in decks that have them.
+        let wilds = wilds.unwrap_or_else(|| {
+            let joker = b'J';
+            if rules.deck.contains(&joker) {
+                vec![joker]
+            } else {
+                Vec::new()
+            }
+        });
+        Ok(Self {
+            rules,
+            wilds,
+            report,
+            sort,
+        })
+    }
+}
+
 fn main() -> Result<()> {
+    let options = Options::from_args()?;
+    let rules = &options.rules;
     let hands = io::stdin()
         .lock()
         .lines()
         .map(|line| {
             let line = line?;
             let (hand, bid) = line.split_once(' ').context("invalid input")?;
-            let hand: [_; 5] = array::from_fn(|i| {
-                let card = hand.as_bytes()[i];
-                CARDS.iter().position(|&c| c == card).unwrap() as u8
-            });
+            let hand = rules.parse_hand(hand)?;
             let bid = bid.parse::<usize>()?;
             Ok((hand, bid))
         })
         .collect::<Result<Vec<_>>>()?;
 
-    let part1 = hands
-        .iter()
-        .copied()
-        .map(|(hand, bid)| {
-            let hand_type = HandType::detect(hand, false);
-            (hand_type, hand, bid)
-        })
-        .sorted_unstable()
-        .enumerate()
-        .map(|(i, (_, _, bid))| (i + 1) * bid as usize)
-        .sum::<usize>();
+    let wild_rules = rules.with_wilds(&options.wilds)?;
+    if let Some(report) = options.report {
+        print_report(
+            &report_rows(rules, &wild_rules, &hands, options.sort),
+            report,
+        );
+        return Ok(());
+    }
 
-    let part2 = hands
-        .iter()
-        .copied()
-        .map(|(mut hand, bid)| {
-            let hand_type = HandType::detect(hand, true);
-            for card in &mut hand {
-                *card = match (*card).cmp(&JOKER) {
-                    Ordering::Less => *card + 1,
-                    Ordering::Equal => 0,
-                    Ordering::Greater => *card,
-                };
-            }
-            (hand_type, hand, bid)
-        })
-        .sorted_unstable()
-        .enumerate()
-        .map(|(i, (_, _, bid))| (i + 1) * bid as usize)
-        .sum::<usize>();
+    let part1 = rules.total_winnings(&hands);
+    let part2 = wild_rules.total_winnings(&hands);
 
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
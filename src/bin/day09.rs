@@ -1,29 +1,181 @@
 use std::io::{self, BufRead};
 
-use anyhow::Result;
-
-fn extrapolate(mut history: Vec<i64>) -> i64 {
-    for i in 0..history.len() {
-        if history[i..].iter().all(|&x| x == history[i]) {
-            history.push(history[i]);
-            for j in (0..i).rev() {
-                for k in (j + 1)..history.len() {
-                    history[k] += history[k - 1];
-                }
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::log;
+use itertools::Itertools;
+use num_integer::Integer;
+
+/// The minimal-degree polynomial through a history sampled at `x = 0, 1, ...`, in Newton form.
+#[derive(Debug, Clone)]
+struct Polynomial {
+    /// The `k`-th forward difference at `x = 0`, for `k` up to the degree.
+    differences: Vec<i64>,
+}
+
+impl Polynomial {
+    /// Fits the history, requiring the constant row of differences to have at least two entries.
+    fn fit(history: &[i64]) -> Result<Self> {
+        ensure!(
+            history.len() >= 2,
+            "history of length {} has too few values to find a degree",
+            history.len()
+        );
+        let mut row = history.to_vec();
+        let mut differences = Vec::new();
+        while row.len() >= 2 {
+            differences.push(row[0]);
+            if row.iter().all(|&x| x == row[0]) {
+                return Ok(Self { differences });
             }
 
-            return history[history.len() - 1];
+            row = row
+                .iter()
+                .tuple_windows()
+                .map(|(&a, &b)| b.checked_sub(a).context("difference overflows"))
+                .try_collect()?;
         }
 
-        for j in ((i + 1)..history.len()).rev() {
-            history[j] -= history[j - 1];
+        bail!(
+            "history of length {} is not a polynomial of degree below {}",
+            history.len(),
+            history.len().saturating_sub(1)
+        )
+    }
+
+    fn degree(&self) -> usize {
+        self.differences.len() - 1
+    }
+
+    fn eval(&self, x: i64) -> Result<i64> {
+        let x = i128::from(x);
+        let mut binomial = 1_i128;
+        let mut value = 0_i128;
+        for (k, &difference) in (0..).zip(&self.differences) {
+            if k > 0 {
+                binomial = binomial.checked_mul(x - k + 1).context("overflow")? / k;
+            }
+            value = binomial
+                .checked_mul(i128::from(difference))
+                .and_then(|term| value.checked_add(term))
+                .context("overflow")?;
+        }
+
+        i64::try_from(value).context("value out of range")
+    }
+
+    /// The coefficients of `1, x, x^2, ...` as reduced `(numerator, denominator)` pairs.
+    fn coefficients(&self) -> Result<Vec<(i128, i128)>> {
+        let degree = self.degree();
+        let mut denominator = 1_i128;
+        for k in 2..=degree {
+            denominator = denominator.checked_mul(k as i128).context("overflow")?;
+        }
+
+        // `falling` holds the coefficients of x (x - 1) ... (x - k + 1), `scale` is degree! / k!.
+        let mut falling = vec![1_i128];
+        let mut scale = denominator;
+        let mut numerators = vec![0_i128; degree + 1];
+        for (k, &difference) in self.differences.iter().enumerate() {
+            if k > 0 {
+                let mut next = vec![0; k + 1];
+                for (i, &c) in falling.iter().enumerate() {
+                    next[i + 1] += c;
+                    next[i] -= c * (k as i128 - 1);
+                }
+                falling = next;
+                scale /= k as i128;
+            }
+
+            let factor = scale
+                .checked_mul(i128::from(difference))
+                .context("overflow")?;
+            for (numerator, &c) in numerators.iter_mut().zip(&falling) {
+                *numerator = c
+                    .checked_mul(factor)
+                    .and_then(|term| numerator.checked_add(term))
+                    .context("overflow")?;
+            }
         }
+
+        Ok(numerators
+            .into_iter()
+            .map(|numerator| {
+                let gcd = numerator.gcd(&denominator);
+                (numerator / gcd, denominator / gcd)
+            })
+            .collect())
+    }
+}
+
+fn format_polynomial(coefficients: &[(i128, i128)]) -> String {
+    let terms = coefficients
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, &(numerator, _))| numerator != 0)
+        .map(|(power, &(numerator, denominator))| {
+            let coefficient = match (denominator, power) {
+                (1, 0) => numerator.to_string(),
+                (1, _) if numerator == 1 => String::new(),
+                (1, _) if numerator == -1 => "-".to_owned(),
+                (1, _) => numerator.to_string(),
+                _ => format!("{numerator}/{denominator} "),
+            };
+            match power {
+                0 => coefficient.trim_end().to_owned(),
+                1 => format!("{coefficient}x"),
+                _ => format!("{coefficient}x^{power}"),
+            }
+        })
+        .join(" + ");
+    if terms.is_empty() {
+        "0".to_owned()
+    } else {
+        terms.replace("+ -", "- ")
     }
+}
 
-    unreachable!()
+#[derive(Debug, Clone)]
+struct Options {
+    forward: i64,
+    backward: i64,
+    fit: bool,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self {
+            forward: 1,
+            backward: 1,
+            fit: false,
+        };
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            if flag == "--fit" {
+                options.fit = true;
+                continue;
+            }
+
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--forward" => options.forward = value.parse().context("invalid step count")?,
+                "--backward" => options.backward = value.parse().context("invalid step count")?,
+                _ => bail!("usage: day09 [--fit] [--forward STEPS] [--backward STEPS]"),
+            }
+        }
+
+        ensure!(
+            options.forward >= 0 && options.backward >= 0,
+            "step counts must not be negative"
+        );
+        Ok(options)
+    }
 }
 
 fn main() -> Result<()> {
+    let options = Options::from_args()?;
     let histories = io::stdin()
         .lock()
         .lines()
@@ -35,15 +187,28 @@ fn main() -> Result<()> {
         })
         .collect::<Result<Vec<_>>>()?;
 
-    let part1: i64 = histories.iter().cloned().map(extrapolate).sum();
-    let part2: i64 = histories
-        .into_iter()
-        .map(|mut history| {
-            history.reverse();
-            history
-        })
-        .map(extrapolate)
-        .sum();
+    let mut part1 = 0_i64;
+    let mut part2 = 0_i64;
+    for (i, history) in histories.iter().enumerate() {
+        let polynomial = Polynomial::fit(history).with_context(|| format!("history {}", i + 1))?;
+        let last = history.len() as i64 - 1;
+        let next = polynomial.eval(last + options.forward)?;
+        let prev = polynomial.eval(-options.backward)?;
+        if options.fit {
+            let coefficients = polynomial.coefficients()?;
+            println!(
+                "History {}: degree {}, p(x) = {}, p({}) = {next}, p({}) = {prev}",
+                i + 1,
+                polynomial.degree(),
+                format_polynomial(&coefficients),
+                last + options.forward,
+                -options.backward,
+            );
+        }
+
+        part1 = part1.checked_add(next).context("overflow")?;
+        part2 = part2.checked_add(prev).context("overflow")?;
+    }
 
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
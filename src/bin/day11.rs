@@ -1,43 +1,143 @@
-use std::io::{self, BufRead};
+use std::{
+    cmp::Reverse,
+    io::{self, BufRead},
+};
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::log;
 use itertools::Itertools;
 
-const COORD_EXPANSION_FACTORS: [usize; 2] = [2, 1_000_000];
+const DEFAULT_EXPANSION_FACTORS: [usize; 2] = [2, 1_000_000];
 
-fn solve(galaxies: &mut [[usize; 2]]) -> [usize; 2] {
+/// Galaxies in reading order, with the number of empty rows and columns before each coordinate.
+#[derive(Debug, Clone)]
+struct Universe {
+    galaxies: Vec<[usize; 2]>,
+    empty_before: [Vec<usize>; 2],
+}
+
+impl Universe {
+    fn new(galaxies: Vec<[usize; 2]>) -> Self {
+        let empty_before = [0, 1].map(|dim| {
+            let size = galaxies
+                .iter()
+                .map(|coord| coord[dim] + 1)
+                .max()
+                .unwrap_or(0);
+            let mut occupied = vec![false; size];
+            for coord in &galaxies {
+                occupied[coord[dim]] = true;
+            }
+            occupied
+                .into_iter()
+                .scan(0, |num_empty, occupied| {
+                    let before = *num_empty;
+                    *num_empty += usize::from(!occupied);
+                    Some(before)
+                })
+                .collect()
+        });
+
+        Self {
+            galaxies,
+            empty_before,
+        }
+    }
+
+    fn expanded(&self, expansion: usize) -> Vec<[usize; 2]> {
+        self.galaxies
+            .iter()
+            .map(|coord| {
+                [0, 1].map(|dim| coord[dim] + self.empty_before[dim][coord[dim]] * (expansion - 1))
+            })
+            .collect()
+    }
+}
+
+fn distance(coord: [usize; 2], coord2: [usize; 2]) -> usize {
+    coord[0].abs_diff(coord2[0]) + coord[1].abs_diff(coord2[1])
+}
+
+/// Sums the distances of all pairs in `O(n log n)`.
+fn distance_sum(mut galaxies: Vec<[usize; 2]>) -> usize {
     (0..2)
         .map(|dim| {
             galaxies.sort_unstable_by_key(|coord| coord[dim]);
-
-            let mut prev = 0;
-            let mut num_expanded = 0;
-            let mut coord_sum_before = [0; 2];
-            let mut ans = [0; 2];
+            let mut coord_sum_before = 0;
+            let mut ans = 0;
             for (num_before, &coord) in galaxies.iter().enumerate() {
-                if let Some(expanded) = (coord[dim] - prev).checked_sub(1) {
-                    num_expanded += expanded;
-                }
-                prev = coord[dim];
-
-                for ((expansion, ans), sum_before) in COORD_EXPANSION_FACTORS
-                    .into_iter()
-                    .zip(&mut ans)
-                    .zip(&mut coord_sum_before)
-                {
-                    let actual_coord = coord[dim] + num_expanded * (expansion - 1);
-                    *ans += actual_coord * num_before - *sum_before;
-                    *sum_before += actual_coord;
-                }
+                ans += coord[dim] * num_before - coord_sum_before;
+                coord_sum_before += coord[dim];
             }
 
             ans
         })
-        .fold([0; 2], |[a1, b1], [a2, b2]| [a1 + a2, b1 + b2])
+        .sum()
+}
+
+/// The `k` closest (or farthest) pairs as `(distance, galaxy, galaxy)`.
+fn extreme_pairs(galaxies: &[[usize; 2]], k: usize, farthest: bool) -> Vec<(usize, usize, usize)> {
+    let pairs = (0..galaxies.len())
+        .tuple_combinations()
+        .map(|(i, j)| (distance(galaxies[i], galaxies[j]), i, j));
+    if farthest {
+        pairs
+            .map(|(dist, i, j)| (Reverse(dist), i, j))
+            .k_smallest(k)
+            .map(|(Reverse(dist), i, j)| (dist, i, j))
+            .collect()
+    } else {
+        pairs.k_smallest(k).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Options {
+    factors: Option<Vec<usize>>,
+    pairs: Vec<(usize, usize)>,
+    closest: Option<usize>,
+    farthest: Option<usize>,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--factors" => {
+                    let factors: Vec<usize> = value
+                        .split(',')
+                        .map(|factor| factor.parse().context("invalid expansion factor"))
+                        .try_collect()?;
+                    ensure!(
+                        factors.iter().all(|&factor| factor >= 1),
+                        "expansion factors must be at least 1"
+                    );
+                    options.factors = Some(factors);
+                }
+                "--pair" => {
+                    let (a, b) = value.split_once(',').context("invalid pair")?;
+                    options.pairs.push((a.parse()?, b.parse()?));
+                }
+                "--closest" => options.closest = Some(value.parse().context("invalid count")?),
+                "--farthest" => options.farthest = Some(value.parse().context("invalid count")?),
+                _ => bail!(
+                    "usage: day11 [--factors F,...] [--pair A,B] [--closest K] [--farthest K]"
+                ),
+            }
+        }
+
+        Ok(options)
+    }
 }
 
 fn main() -> Result<()> {
-    let mut galaxies: Vec<_> = io::stdin()
+    let options = Options::from_args()?;
+    let galaxies: Vec<_> = io::stdin()
         .lock()
         .lines()
         .enumerate()
@@ -51,9 +151,50 @@ fn main() -> Result<()> {
         })
         .flatten_ok()
         .collect::<Result<_>>()?;
-    let [part1, part2] = solve(&mut galaxies);
+    let universe = Universe::new(galaxies);
+    let num_galaxies = universe.galaxies.len();
+    for &(a, b) in &options.pairs {
+        ensure!(
+            (1..=num_galaxies).contains(&a) && (1..=num_galaxies).contains(&b),
+            "no such galaxy pair: {},{}",
+            a,
+            b
+        );
+    }
+
+    let factors = options
+        .factors
+        .as_deref()
+        .unwrap_or(&DEFAULT_EXPANSION_FACTORS);
+    let mut sums = Vec::with_capacity(factors.len());
+    for &factor in factors {
+        let galaxies = universe.expanded(factor);
+        for &(a, b) in &options.pairs {
+            let dist = distance(galaxies[a - 1], galaxies[b - 1]);
+            println!("Factor {factor}, galaxies {a} and {b}: {dist}");
+        }
+        for (name, k, farthest) in [
+            ("closest", options.closest, false),
+            ("farthest", options.farthest, true),
+        ] {
+            for (dist, i, j) in k.map_or(Vec::new(), |k| extreme_pairs(&galaxies, k, farthest)) {
+                println!(
+                    "Factor {factor}, {name}: galaxies {} and {}: {dist}",
+                    i + 1,
+                    j + 1
+                );
+            }
+        }
+        sums.push(distance_sum(galaxies));
+    }
 
-    println!("Part 1: {part1}");
-    println!("Part 2: {part2}");
+    if options.factors.is_some() {
+        for (factor, sum) in factors.iter().zip(sums) {
+            println!("Factor {factor}: {sum}");
+        }
+    } else {
+        println!("Part 1: {}", sums[0]);
+        println!("Part 2: {}", sums[1]);
+    }
     Ok(())
 }
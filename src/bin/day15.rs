@@ -1,51 +1,202 @@
 use std::{
-    array,
+    hint::black_box,
     io::{self, BufRead},
+    time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::{
+    lens_map::{hash, LensMap},
+    log,
+};
+
+const LANES: usize = 32;
+
+/// Sums the HASH of all comma-separated steps, splitting the input into `LANES` runs of whole steps
+/// that are hashed side by side.
+fn hash_sum(input: &[u8]) -> u32 {
+    let mut lanes = [&input[..0]; LANES];
+    let mut rest = input;
+    for (lane, slot) in lanes.iter_mut().enumerate() {
+        let target = rest.len() / (LANES - lane);
+        let end = rest[target..]
+            .iter()
+            .position(|&c| c == b',')
+            .map_or(rest.len(), |i| target + i + 1);
+        (*slot, rest) = rest.split_at(end);
+    }
+
+    let common_len = lanes.iter().map(|lane| lane.len()).min().unwrap_or(0);
+    let mut hashes = [0_u8; LANES];
+    let mut sums = [0_u32; LANES];
+    let mut columns = [0_u8; LANES];
+    for i in 0..common_len {
+        for (column, lane) in columns.iter_mut().zip(&lanes) {
+            *column = lane[i];
+        }
+        for ((&c, hash), sum) in columns.iter().zip(&mut hashes).zip(&mut sums) {
+            let is_step_end = u8::from(c == b',').wrapping_neg();
+            *sum += u32::from(*hash & is_step_end);
+            *hash = hash.wrapping_add(c).wrapping_mul(17) & !is_step_end;
+        }
+    }
+
+    let mut sum = 0;
+    for lane in 0..LANES {
+        sum += sums[lane];
+        let mut hash = hashes[lane];
+        for &c in &lanes[lane][common_len..] {
+            if c == b',' {
+                sum += u32::from(hash);
+                hash = 0;
+            } else {
+                hash = hash.wrapping_add(c).wrapping_mul(17);
+            }
+        }
+        sum += u32::from(hash);
+    }
+
+    sum
+}
+
+fn hash_sum_scalar(input: &[u8]) -> u32 {
+    input
+        .split(|&c| c == b',')
+        .map(|step| u32::from(hash(step)))
+        .sum()
+}
+
+#[derive(Debug, Clone, Default)]
+struct Options {
+    steps: bool,
+    stop_after: Option<usize>,
+    bench: Option<u32>,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            if flag == "--steps" {
+                options.steps = true;
+                continue;
+            }
+
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--stop-after" => {
+                    options.stop_after = Some(value.parse().context("invalid step count")?)
+                }
+                "--bench" => options.bench = Some(value.parse().context("invalid iterations")?),
+                _ => bail!("usage: day15 [--steps] [--stop-after N] [--bench ITERATIONS]"),
+            }
+        }
+
+        Ok(options)
+    }
+}
 
-fn hash(s: &str) -> u8 {
-    s.bytes()
-        .fold(0_u8, |hash, c| hash.wrapping_add(c).wrapping_mul(17))
+/// Runs `f` `iterations` times, returning its last result and the mean time per run.
+fn bench(iterations: u32, mut f: impl FnMut() -> u32) -> (u32, Duration) {
+    let start = Instant::now();
+    let mut result = 0;
+    for _ in 0..iterations.max(1) {
+        result = black_box(f());
+    }
+    (result, start.elapsed() / iterations.max(1))
 }
 
 fn main() -> Result<()> {
+    let options = Options::from_args()?;
     let input = io::stdin().lock().lines().next().context("empty input")??;
-    let part1: u32 = input.split(',').map(|s| u32::from(hash(s))).sum();
+    let part1 = hash_sum(input.as_bytes());
+    if let Some(iterations) = options.bench {
+        let scalar = bench(iterations, || hash_sum_scalar(input.as_bytes()));
+        let lanes = bench(iterations, || hash_sum(input.as_bytes()));
+        ensure!(
+            scalar.0 == part1 && lanes.0 == part1,
+            "sums differ: scalar {}, lanes {}",
+            scalar.0,
+            lanes.0
+        );
+        println!("Scalar: {:?} per run", scalar.1);
+        println!("Lanes: {:?} per run", lanes.1);
+    }
 
-    let mut map: [Vec<(&str, u32)>; 256] = array::from_fn(|_| Vec::new());
-    for instr in input.split(',') {
+    let mut map = LensMap::default();
+    for instr in input
+        .split(',')
+        .take(options.stop_after.unwrap_or(usize::MAX))
+    {
         if let Some(label) = instr.strip_suffix('-') {
-            let h = usize::from(hash(label));
-            if let Some(idx) = map[h].iter().position(|(l, _)| l == &label) {
-                map[h].remove(idx);
-            }
+            map.remove(&label);
         } else {
             let (label, focal_length) = instr.split_once('=').context("invalid instruction")?;
             let focal_length: u32 = focal_length.parse().context("invalid focal length")?;
-            let h = usize::from(hash(label));
-            if let Some((_, prev_focal_length)) = map[h].iter_mut().find(|(l, _)| l == &label) {
-                *prev_focal_length = focal_length;
-            } else {
-                map[h].push((label, focal_length));
-            }
+            map.insert(label, focal_length);
+        }
+
+        if options.steps {
+            println!("After \"{instr}\":\n{map}");
         }
     }
-    let part2: u32 = map
-        .iter()
-        .enumerate()
-        .map(|(i, entries)| {
-            (i as u32 + 1)
-                * entries
-                    .iter()
-                    .enumerate()
-                    .map(|(j, (_, focal_length))| (j as u32 + 1) * focal_length)
-                    .sum::<u32>()
-        })
-        .sum();
+    let part2 = map.focusing_power();
 
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &[u8]) {
+        assert_eq!(
+            hash_sum(input),
+            hash_sum_scalar(input),
+            "{:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    #[test]
+    fn example() {
+        let input = b"rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
+        assert_eq!(hash_sum(input), 1320);
+        check(input);
+    }
+
+    #[test]
+    fn edge_cases() {
+        check(b"");
+        check(b",");
+        check(b"HASH");
+        check(b"rn=1,,cm-,,,qp=3,");
+        check(",,".repeat(LANES).as_bytes());
+        // Nearly every lane boundary lands exactly on a comma.
+        check(",a".repeat(LANES).as_bytes());
+        check("ab,".repeat(3 * LANES).as_bytes());
+    }
+
+    #[test]
+    fn comma_spacings() {
+        for len in 0..4 * LANES {
+            for spacing in 1..8 {
+                let input: Vec<u8> = (0..len)
+                    .map(|i| {
+                        if i % spacing == 0 {
+                            b','
+                        } else {
+                            b'a' + (i % 26) as u8
+                        }
+                    })
+                    .collect();
+                check(&input);
+            }
+        }
+    }
+}
@@ -1,47 +1,176 @@
-use std::{io, ops::Add, str::FromStr};
+use std::{
+    fmt::{self, Display, Write},
+    io,
+    ops::Range,
+    str::FromStr,
+};
 
-use anyhow::{bail, Context, Ok, Result};
+use anyhow::{bail, ensure, Context, Ok, Result};
+use aoc2023::log;
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-enum Category {
-    ExtremlyGoodLooking,
-    Musical,
-    Aerodynamic,
-    Shiny,
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
 }
 
-impl Category {
-    fn as_index(self) -> usize {
+impl Comparison {
+    /// The values satisfying `_ <op> value`.
+    fn matching(self, value: u64) -> Range<u64> {
         match self {
-            Self::ExtremlyGoodLooking => 0,
-            Self::Musical => 1,
-            Self::Aerodynamic => 2,
-            Self::Shiny => 3,
+            Self::Less => 0..value,
+            Self::LessEqual => 0..value.saturating_add(1),
+            Self::Greater => value.saturating_add(1)..u64::MAX,
+            Self::GreaterEqual => value..u64::MAX,
+            Self::Equal => value..value.saturating_add(1),
         }
     }
 }
 
-impl FromStr for Category {
+impl Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::Equal => "==",
+        };
+        f.write_str(op)
+    }
+}
+
+impl FromStr for Comparison {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self> {
         match s {
-            "x" => Ok(Self::ExtremlyGoodLooking),
-            "m" => Ok(Self::Musical),
-            "a" => Ok(Self::Aerodynamic),
-            "s" => Ok(Self::Shiny),
-            _ => bail!("invalid category: {}", s),
+            "<" => Ok(Self::Less),
+            "<=" => Ok(Self::LessEqual),
+            ">" => Ok(Self::Greater),
+            ">=" => Ok(Self::GreaterEqual),
+            "==" => Ok(Self::Equal),
+            _ => bail!("invalid comparison: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Categories {
+    names: Vec<String>,
+    bounds: Range<u64>,
+}
+
+impl Default for Categories {
+    fn default() -> Self {
+        Self {
+            names: ["x", "m", "a", "s"].map(String::from).to_vec(),
+            bounds: 1..4001,
         }
     }
 }
 
+impl Categories {
+    fn index(&self, name: &str) -> Result<usize> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .with_context(|| format!("invalid category: {name}"))
+    }
+
+    fn full_spec(&self) -> PartSpec {
+        vec![self.bounds.clone(); self.names.len()]
+    }
+
+    fn parse_condition(&self, predicate: &str) -> Result<(usize, Comparison, u64)> {
+        let op_start = predicate
+            .find(['<', '>', '='])
+            .with_context(|| format!("missing comparison: {predicate}"))?;
+        let op_end = predicate[op_start..]
+            .find(|c| !matches!(c, '<' | '>' | '='))
+            .map_or(predicate.len(), |i| op_start + i);
+        Ok((
+            self.index(predicate[..op_start].trim())?,
+            predicate[op_start..op_end].parse()?,
+            predicate[op_end..].trim().parse()?,
+        ))
+    }
+
+    fn parse_rule(&self, spec: &str, workflow_labels: &FxHashMap<&str, usize>) -> Result<Rule> {
+        let (predicate, workflow) = spec.split_once(':').context("invalid rule")?;
+        let (category, comparison, value) = self.parse_condition(predicate)?;
+        Ok(Rule {
+            category,
+            comparison,
+            value,
+            workflow: *workflow_labels
+                .get(workflow)
+                .with_context(|| format!("unknown workflow: {workflow}"))?,
+        })
+    }
+
+    /// Parses conditions joined by `&&` into the allowed range of each constrained category.
+    fn parse_query(&self, query: &str) -> Result<Vec<(usize, Range<u64>)>> {
+        query
+            .split("&&")
+            .filter(|condition| !condition.trim().is_empty())
+            .map(|condition| {
+                let (category, comparison, value) = self.parse_condition(condition)?;
+                Ok((category, comparison.matching(value)))
+            })
+            .collect()
+    }
+
+    fn format_rule(&self, rule: &Rule) -> String {
+        let name = &self.names[rule.category];
+        format!("{name}{}{}", rule.comparison, rule.value)
+    }
+
+    fn format_spec(&self, part_spec: &PartSpec) -> String {
+        self.names
+            .iter()
+            .zip(part_spec)
+            .map(|(name, range)| format!("{name}={}..{}", range.start, range.end))
+            .join(" ")
+    }
+
+    fn parse_part(&self, spec: &str) -> Result<Vec<u64>> {
+        let mut part = vec![None; self.names.len()];
+        let ratings = spec
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .context("invalid part")?;
+        for rating in ratings.split(',') {
+            let (name, value) = rating.split_once('=').context("invalid rating")?;
+            let slot = &mut part[self.index(name)?];
+            ensure!(slot.is_none(), "duplicate rating: {}", name);
+            let value = value.parse()?;
+            ensure!(
+                self.bounds.contains(&value),
+                "rating out of bounds: {}={}",
+                name,
+                value
+            );
+            *slot = Some(value);
+        }
+
+        part.into_iter()
+            .zip(&self.names)
+            .map(|(value, name)| value.with_context(|| format!("missing rating: {name}")))
+            .collect()
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 struct Rule {
-    category: Category,
-    larger: bool,
-    value: u32,
+    category: usize,
+    comparison: Comparison,
+    value: u64,
     workflow: usize,
 }
 
@@ -51,7 +180,493 @@ struct Workflow {
     fallback: usize,
 }
 
+impl Workflow {
+    /// The first matching rule (if any) and the workflow it sends `part` to.
+    fn apply(&self, part: &[u64]) -> (Option<&Rule>, usize) {
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.comparison
+                    .matching(rule.value)
+                    .contains(&part[rule.category])
+            })
+            .map_or((None, self.fallback), |rule| (Some(rule), rule.workflow))
+    }
+}
+
+/// Lists the workflows visited by `part` and the rule that matched in each.
+fn explain(
+    workflows: &[Workflow],
+    workflow_names: &[&str],
+    categories: &Categories,
+    mut workflow_index: usize,
+    part: &[u64],
+) -> String {
+    let mut steps = Vec::new();
+    while let Some(workflow) = workflows.get(workflow_index) {
+        let (rule, next) = workflow.apply(part);
+        let rule = rule.map_or("fallback".to_owned(), |rule| categories.format_rule(rule));
+        steps.push(format!(
+            "{}: {rule} -> {}",
+            workflow_names[workflow_index], workflow_names[next]
+        ));
+        workflow_index = next;
+    }
+
+    steps.join("; ")
+}
+
+type PartSpec = Vec<Range<u64>>;
+
+fn volume(part_spec: &PartSpec) -> u128 {
+    part_spec
+        .iter()
+        .map(|range| u128::from(range.end.saturating_sub(range.start)))
+        .product()
+}
+
+/// The accepted parts as disjoint boxes.
+#[derive(Debug, Clone)]
+struct Region {
+    boxes: Vec<PartSpec>,
+}
+
+impl Region {
+    fn new(tree: &DecisionTree, part_spec: PartSpec) -> Self {
+        let mut boxes = Vec::new();
+        tree.accepted_boxes(tree.root, part_spec, &mut boxes);
+        Self { boxes }
+    }
+
+    /// Accepted parts per single rating of `category`, grouped into ranges of equal counts.
+    fn marginal(&self, category: usize) -> Vec<(Range<u64>, u128)> {
+        let mut events = self
+            .boxes
+            .iter()
+            .flat_map(|part_spec| {
+                let range = &part_spec[category];
+                let per_value = volume(part_spec) / u128::from(range.end - range.start);
+                [
+                    (range.start, true, per_value),
+                    (range.end, false, per_value),
+                ]
+            })
+            .collect_vec();
+        events.sort_unstable_by_key(|&(value, _, _)| value);
+
+        let mut marginal: Vec<(Range<u64>, u128)> = Vec::new();
+        let mut count = 0;
+        let mut i = 0;
+        while i < events.len() {
+            let value = events[i].0;
+            while let Some(&(_, starts, per_value)) = events.get(i).filter(|e| e.0 == value) {
+                if starts {
+                    count += per_value;
+                } else {
+                    count -= per_value;
+                }
+                i += 1;
+            }
+
+            let Some(&(next_value, _, _)) = events.get(i) else {
+                break;
+            };
+            match marginal.last_mut() {
+                Some((range, last)) if *last == count && range.end == value => {
+                    range.end = next_value
+                }
+                _ if count > 0 => marginal.push((value..next_value, count)),
+                _ => {}
+            }
+        }
+
+        marginal
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Decision {
+    Accept,
+    Reject,
+    /// An index into [`DecisionTree::splits`].
+    Split(usize),
+}
+
+/// Continues with `below` if the rating is less than `threshold`, with `above` otherwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Split {
+    category: usize,
+    threshold: u64,
+    below: Decision,
+    above: Decision,
+}
+
+/// All workflows flattened into a single tree starting at `root`, with identical subtrees stored
+/// once.
+#[derive(Debug)]
+struct DecisionTree {
+    splits: Vec<Split>,
+    split_ids: FxHashMap<Split, usize>,
+    /// The categories tested anywhere below each split, sorted.
+    tested: Vec<Vec<usize>>,
+    root: Decision,
+}
+
+impl DecisionTree {
+    fn new(
+        workflows: &[Workflow],
+        accept_workflow: usize,
+        in_workflow: usize,
+        part_spec: PartSpec,
+    ) -> Self {
+        let mut tree = Self {
+            splits: Vec::new(),
+            split_ids: FxHashMap::default(),
+            tested: Vec::new(),
+            root: Decision::Reject,
+        };
+        tree.root = tree.compile(workflows, accept_workflow, in_workflow, part_spec);
+        tree
+    }
+
+    fn tested(&self, decision: Decision) -> &[usize] {
+        match decision {
+            Decision::Split(id) => &self.tested[id],
+            _ => &[],
+        }
+    }
+
+    fn split(
+        &mut self,
+        category: usize,
+        threshold: u64,
+        below: Decision,
+        above: Decision,
+    ) -> Decision {
+        if below == above {
+            return below;
+        }
+
+        let split = Split {
+            category,
+            threshold,
+            below,
+            above,
+        };
+        if let Some(&id) = self.split_ids.get(&split) {
+            return Decision::Split(id);
+        }
+
+        let tested = self
+            .tested(below)
+            .iter()
+            .chain(self.tested(above))
+            .chain([&category])
+            .copied()
+            .sorted_unstable()
+            .dedup()
+            .collect();
+        let id = self.splits.len();
+        self.splits.push(split);
+        self.split_ids.insert(split, id);
+        self.tested.push(tested);
+        Decision::Split(id)
+    }
+
+    fn compile(
+        &mut self,
+        workflows: &[Workflow],
+        accept_workflow: usize,
+        workflow_index: usize,
+        part_spec: PartSpec,
+    ) -> Decision {
+        let Some(workflow) = workflows.get(workflow_index) else {
+            return if workflow_index == accept_workflow {
+                Decision::Accept
+            } else {
+                Decision::Reject
+            };
+        };
+
+        self.compile_rules(
+            workflows,
+            accept_workflow,
+            &workflow.rules,
+            workflow.fallback,
+            part_spec,
+        )
+    }
+
+    fn compile_rules(
+        &mut self,
+        workflows: &[Workflow],
+        accept_workflow: usize,
+        rules: &[Rule],
+        fallback: usize,
+        part_spec: PartSpec,
+    ) -> Decision {
+        let Some((rule, rules)) = rules.split_first() else {
+            return self.compile(workflows, accept_workflow, fallback, part_spec);
+        };
+
+        let range = part_spec[rule.category].clone();
+        let matching = rule.comparison.matching(rule.value);
+        let start = matching.start.clamp(range.start, range.end);
+        let end = matching.end.clamp(start, range.end);
+
+        // Rules that can never fire given the earlier rules are dropped.
+        if start == end {
+            return self.compile_rules(workflows, accept_workflow, rules, fallback, part_spec);
+        }
+
+        let with_range = |sub_range| {
+            let mut spec = part_spec.clone();
+            spec[rule.category] = sub_range;
+            spec
+        };
+        let matched = self.compile(
+            workflows,
+            accept_workflow,
+            rule.workflow,
+            with_range(start..end),
+        );
+        if start == range.start && end == range.end {
+            return matched;
+        }
+
+        // The remaining rules are compiled once for the values on both sides of the match, within
+        // their exact range unless the match lies strictly inside.
+        let rest_range = match (range.start < start, end < range.end) {
+            (true, false) => range.start..start,
+            (false, true) => end..range.end,
+            _ => range.clone(),
+        };
+        let rest = self.compile_rules(
+            workflows,
+            accept_workflow,
+            rules,
+            fallback,
+            with_range(rest_range),
+        );
+
+        let mut decision = matched;
+        if end < range.end {
+            decision = self.split(rule.category, end, decision, rest);
+        }
+        if range.start < start {
+            decision = self.split(rule.category, start, rest, decision);
+        }
+
+        decision
+    }
+
+    fn accepts(&self, part: &[u64]) -> bool {
+        let mut node = self.root;
+        loop {
+            match node {
+                Decision::Accept => return true,
+                Decision::Reject => return false,
+                Decision::Split(id) => {
+                    let split = &self.splits[id];
+                    node = if part[split.category] < split.threshold {
+                        split.below
+                    } else {
+                        split.above
+                    }
+                }
+            }
+        }
+    }
+
+    /// Splits `part_spec` at the threshold of `split`, clamped to its range.
+    fn split_spec(split: &Split, part_spec: PartSpec) -> (PartSpec, PartSpec) {
+        let range = &part_spec[split.category];
+        let threshold = split.threshold.clamp(range.start, range.end);
+        let mut below_spec = part_spec.clone();
+        below_spec[split.category].end = threshold;
+        let mut above_spec = part_spec;
+        above_spec[split.category].start = threshold;
+        (below_spec, above_spec)
+    }
+
+    /// Counts the accepted parts whose ratings lie within all `constraints`.
+    fn count(&self, mut part_spec: PartSpec, constraints: &[(usize, Range<u64>)]) -> u128 {
+        for (category, range) in constraints {
+            let spec_range = &mut part_spec[*category];
+            spec_range.start = spec_range.start.max(range.start);
+            spec_range.end = spec_range.end.min(range.end).max(spec_range.start);
+        }
+
+        self.count_from(self.root, part_spec, &mut FxHashMap::default())
+    }
+
+    /// Counts per split with the categories it never tests narrowed to a single value, so that
+    /// subtrees reached from several sides of an earlier split are only counted once.
+    fn count_from(
+        &self,
+        decision: Decision,
+        mut part_spec: PartSpec,
+        counts: &mut FxHashMap<(usize, PartSpec), u128>,
+    ) -> u128 {
+        let id = match decision {
+            Decision::Accept => return volume(&part_spec),
+            Decision::Reject => return 0,
+            Decision::Split(id) => id,
+        };
+
+        let mut untested_volume = 1;
+        for (category, range) in part_spec.iter_mut().enumerate() {
+            if self.tested[id].binary_search(&category).is_err() {
+                untested_volume *= u128::from(range.end.saturating_sub(range.start));
+                *range = 0..1;
+            }
+        }
+        if untested_volume == 0 {
+            return 0;
+        }
+        if let Some(&count) = counts.get(&(id, part_spec.clone())) {
+            return untested_volume * count;
+        }
+
+        let split = self.splits[id];
+        let (below_spec, above_spec) = Self::split_spec(&split, part_spec.clone());
+        let count = self.count_from(split.below, below_spec, counts)
+            + self.count_from(split.above, above_spec, counts);
+        counts.insert((id, part_spec), count);
+        untested_volume * count
+    }
+
+    /// Collects the disjoint boxes of accepted parts within `part_spec`.
+    fn accepted_boxes(&self, decision: Decision, part_spec: PartSpec, boxes: &mut Vec<PartSpec>) {
+        match decision {
+            Decision::Accept => {
+                if volume(&part_spec) > 0 {
+                    boxes.push(part_spec);
+                }
+            }
+            Decision::Reject => {}
+            Decision::Split(id) => {
+                let split = &self.splits[id];
+                let (below_spec, above_spec) = Self::split_spec(split, part_spec);
+                self.accepted_boxes(split.below, below_spec, boxes);
+                self.accepted_boxes(split.above, above_spec, boxes);
+            }
+        }
+    }
+
+    /// Prints the tree back as workflows, sharing identical subtrees.
+    fn to_workflows(&self, categories: &Categories) -> String {
+        let mut labels = FxHashMap::default();
+        let mut queue = vec![(self.root, "in".to_owned())];
+        let mut output = String::new();
+        while let Some((mut node, label)) = queue.pop() {
+            let mut rules = Vec::new();
+            while let Decision::Split(id) = node {
+                let split = &self.splits[id];
+                let name = &categories.names[split.category];
+                let below_label = Self::label(split.below, &mut labels, &mut queue);
+                rules.push(format!("{name}<{}:{below_label}", split.threshold));
+                node = split.above;
+            }
+
+            rules.push(Self::label(node, &mut labels, &mut queue));
+            writeln!(output, "{label}{{{}}}", rules.join(",")).unwrap();
+        }
+
+        output
+    }
+
+    fn label(
+        decision: Decision,
+        labels: &mut FxHashMap<usize, String>,
+        queue: &mut Vec<(Decision, String)>,
+    ) -> String {
+        match decision {
+            Decision::Accept => "A".to_owned(),
+            Decision::Reject => "R".to_owned(),
+            Decision::Split(id) => {
+                let next = labels.len();
+                labels
+                    .entry(id)
+                    .or_insert_with(|| {
+                        let label = format!("w{next}");
+                        queue.push((decision, label.clone()));
+                        label
+                    })
+                    .clone()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Options {
+    categories: Categories,
+    simplify: bool,
+    explain: bool,
+    marginals: bool,
+    counts: Vec<String>,
+    checks: Vec<String>,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--simplify" => {
+                    options.simplify = true;
+                    continue;
+                }
+                "--explain" => {
+                    options.explain = true;
+                    continue;
+                }
+                "--marginals" => {
+                    options.marginals = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--categories" => {
+                    let names = value.split(',').map(String::from).collect_vec();
+                    ensure!(names.iter().all_unique(), "duplicate category");
+                    ensure!(
+                        names.iter().all(|name| !name.is_empty()
+                            && !name.contains(['<', '>', '=', ':', '{', '}'])),
+                        "invalid category names: {}",
+                        value
+                    );
+                    options.categories.names = names;
+                }
+                "--bounds" => {
+                    let (start, end) = value.split_once("..").context("invalid bounds")?;
+                    let bounds = start.parse()?..end.parse()?;
+                    ensure!(!bounds.is_empty(), "empty bounds: {}", value);
+                    options.categories.bounds = bounds;
+                }
+                "--count" => options.counts.push(value),
+                "--check" => options.checks.push(value),
+                _ => bail!(
+                    "usage: day19 [--simplify] [--explain] [--marginals] [--count QUERY] \
+                     [--check PART] [--categories NAME,...] [--bounds START..END]"
+                ),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
 fn main() -> Result<()> {
+    let options = Options::from_args()?;
+    let categories = &options.categories;
     let input = io::read_to_string(io::stdin().lock())?;
 
     let workflow_labels: FxHashMap<_, _> = input
@@ -62,7 +677,7 @@ fn main() -> Result<()> {
         .enumerate()
         .map(|(i, label)| Ok((label?, i)))
         .try_collect()?;
-    let in_workflow = workflow_labels["in"];
+    let in_workflow = *workflow_labels.get("in").context("missing in workflow")?;
     let accept_workflow = workflow_labels["A"];
 
     let workflows: Vec<_> = input
@@ -72,99 +687,103 @@ fn main() -> Result<()> {
             let (_label, tail) = line.split_once('{').context("invalid workflow")?;
             let mut rules_iter = tail.trim_end_matches('}').split(',');
             let fallback = rules_iter.next_back().context("empty workflow")?;
-            let fallback = workflow_labels[fallback];
+            let fallback = *workflow_labels
+                .get(fallback)
+                .with_context(|| format!("unknown workflow: {fallback}"))?;
 
             let rules: Vec<_> = rules_iter
-                .map(|rule_spec| {
-                    let (predicate, workflow) =
-                        rule_spec.split_once(':').context("invalid rule")?;
-                    let category = predicate[..1].parse::<Category>()?;
-                    let larger = &predicate[1..2] == ">";
-                    let value = predicate[2..].parse::<u32>()?;
-                    let workflow = workflow_labels[workflow];
-                    Ok(Rule {
-                        category,
-                        larger,
-                        value,
-                        workflow,
-                    })
-                })
+                .map(|rule_spec| categories.parse_rule(rule_spec, &workflow_labels))
                 .try_collect()?;
 
             Ok(Workflow { rules, fallback })
         })
         .try_collect()?;
 
-    let part1 = input
+    let tree = DecisionTree::new(
+        &workflows,
+        accept_workflow,
+        in_workflow,
+        categories.full_spec(),
+    );
+    if options.simplify {
+        print!("{}", tree.to_workflows(categories));
+        return Ok(());
+    }
+
+    let parts: Vec<_> = input
         .lines()
         .skip_while(|line| !line.is_empty())
         .skip(1)
-        .map(|line| {
-            let (x, m, a, s) = line[1..]
-                .trim_end_matches('}')
-                .split(',')
-                .map(|part| part[2..].parse::<u32>())
-                .collect_tuple()
-                .context("invalid part")?;
-            Ok([x?, m?, a?, s?])
-        })
-        .filter_ok(|&part| {
-            let mut workflow_index = in_workflow;
-            while let Some(workflow) = workflows.get(workflow_index) {
-                workflow_index = workflow
-                    .rules
-                    .iter()
-                    .find_map(|rule| {
-                        let value = part[rule.category.as_index()];
-                        let matches = if rule.larger {
-                            value > rule.value
-                        } else {
-                            value < rule.value
-                        };
-                        matches.then_some(rule.workflow)
-                    })
-                    .unwrap_or(workflow.fallback);
-            }
-            workflow_index == accept_workflow
-        })
-        .map_ok(|part| part.into_iter().sum::<u32>())
-        .fold_ok(0, Add::add)?;
-
-    let mut part2 = 0;
-    let mut queue = vec![(in_workflow, [(); 4].map(|_| 1..4001))];
-    'outer: while let Some((workflow_index, mut part_spec)) = queue.pop() {
-        let Some(workflow) = workflows.get(workflow_index) else {
-            if workflow_index == accept_workflow {
-                part2 += part_spec
-                    .iter()
-                    .map(|range| u64::from(range.end - range.start))
-                    .product::<u64>();
-            }
-            continue;
-        };
+        .map(|line| categories.parse_part(line))
+        .try_collect()?;
 
-        for rule in &workflow.rules {
-            let cat_index = rule.category.as_index();
-            let mut split_spec = part_spec.clone();
-            if rule.larger {
-                split_spec[cat_index].start = rule.value + 1;
-                part_spec[cat_index].end = rule.value + 1;
-            } else {
-                split_spec[cat_index].end = rule.value;
-                part_spec[cat_index].start = rule.value;
-            }
+    if options.explain {
+        let mut workflow_names = vec![""; workflow_labels.len()];
+        for (&label, &i) in &workflow_labels {
+            workflow_names[i] = label;
+        }
+        for part in &parts {
+            let ratings = categories
+                .names
+                .iter()
+                .zip(part)
+                .map(|(name, value)| format!("{name}={value}"))
+                .join(",");
+            let trace = explain(&workflows, &workflow_names, categories, in_workflow, part);
+            println!("{{{ratings}}}: {trace}");
+        }
+        for part_spec in &Region::new(&tree, categories.full_spec()).boxes {
+            println!(
+                "{}: {}",
+                categories.format_spec(part_spec),
+                volume(part_spec)
+            );
+        }
+    }
 
-            if !split_spec[cat_index].is_empty() {
-                queue.push((rule.workflow, split_spec));
-            }
-            if part_spec[cat_index].is_empty() {
-                continue 'outer;
+    for query in &options.counts {
+        let count = tree.count(categories.full_spec(), &categories.parse_query(query)?);
+        println!("Count {query}: {count}");
+    }
+    for part in &options.checks {
+        let verdict = if tree.accepts(&categories.parse_part(part)?) {
+            "accepted"
+        } else {
+            "rejected"
+        };
+        println!("Check {part}: {verdict}");
+    }
+    if options.marginals {
+        let region = Region::new(&tree, categories.full_spec());
+        for (category, name) in categories.names.iter().enumerate() {
+            for (range, count) in region.marginal(category) {
+                println!("{name}={}..{}: {count}", range.start, range.end);
             }
         }
-
-        queue.push((workflow.fallback, part_spec));
     }
 
+    let accepted_by_workflows = |part: &[u64]| {
+        let mut workflow_index = in_workflow;
+        while let Some(workflow) = workflows.get(workflow_index) {
+            (_, workflow_index) = workflow.apply(part);
+        }
+        workflow_index == accept_workflow
+    };
+    let part1: u64 = parts
+        .iter()
+        .filter(|part| {
+            let accepted = tree.accepts(part);
+            debug_assert_eq!(
+                accepted,
+                accepted_by_workflows(part),
+                "decision tree disagrees with the workflows on {part:?}"
+            );
+            accepted
+        })
+        .map(|part| part.iter().sum::<u64>())
+        .sum();
+    let part2 = tree.count(categories.full_spec(), &[]);
+
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
     Ok(())
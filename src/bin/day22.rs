@@ -1,13 +1,16 @@
 use std::{
     collections::VecDeque,
+    fmt::Write,
     io::{self, BufRead},
     str::FromStr,
 };
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::log;
 use itertools::{iproduct, Itertools};
+use rustc_hash::FxHashMap;
 
-const X_Y_BOUND: usize = 10;
+const MAX_DENSE_AREA: usize = 1 << 20;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Point {
@@ -25,42 +28,98 @@ impl FromStr for Point {
             .map(usize::from_str)
             .collect_tuple()
             .context("invalid number of coordinates")?;
-        let x = x?;
-        let y = y?;
-        ensure!((0..X_Y_BOUND).contains(&x), "x out of range");
-        ensure!((0..X_Y_BOUND).contains(&y), "y out of range");
-        Ok(Self { x, y, z: z? })
+        Ok(Self {
+            x: x?,
+            y: y?,
+            z: z?,
+        })
     }
 }
 
-fn main() -> Result<()> {
-    let mut bricks: Vec<_> = io::stdin()
-        .lock()
-        .lines()
-        .map(|line| {
-            let line = line?;
-            let (from, to) = line.split_once('~').context("invalid line")?;
-            let from = Point::from_str(from)?;
-            let to = Point::from_str(to)?;
-            ensure!(
-                from.x <= to.x && from.y <= to.y && from.z <= to.z,
-                "coordinates out of order"
-            );
-            Ok((from, to))
-        })
-        .try_collect()?;
-    bricks.sort_unstable_by_key(|(from, _)| from.z);
+/// Height and topmost brick for each x/y column.
+#[derive(Debug, Clone)]
+enum HeightMap {
+    Dense {
+        min_x: usize,
+        min_y: usize,
+        width: usize,
+        columns: Vec<(usize, usize)>,
+    },
+    Sparse(FxHashMap<(usize, usize), (usize, usize)>),
+}
+
+impl HeightMap {
+    const EMPTY: (usize, usize) = (0, usize::MAX);
 
-    let mut height_map = [[(0, usize::MAX); X_Y_BOUND]; X_Y_BOUND];
+    fn for_bricks(bricks: &[(Point, Point, usize)]) -> Self {
+        let (Some(min_x), Some(max_x)) = (
+            bricks.iter().map(|(from, _, _)| from.x).min(),
+            bricks.iter().map(|(_, to, _)| to.x).max(),
+        ) else {
+            return Self::Sparse(FxHashMap::default());
+        };
+        let min_y = bricks.iter().map(|(from, _, _)| from.y).min().unwrap_or(0);
+        let max_y = bricks.iter().map(|(_, to, _)| to.y).max().unwrap_or(0);
+
+        let width = max_x - min_x + 1;
+        match width.checked_mul(max_y - min_y + 1) {
+            Some(area) if area <= MAX_DENSE_AREA => Self::Dense {
+                min_x,
+                min_y,
+                width,
+                columns: vec![Self::EMPTY; area],
+            },
+            _ => Self::Sparse(FxHashMap::default()),
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> (usize, usize) {
+        match self {
+            Self::Dense {
+                min_x,
+                min_y,
+                width,
+                columns,
+            } => columns[(y - min_y) * width + (x - min_x)],
+            Self::Sparse(columns) => columns.get(&(x, y)).copied().unwrap_or(Self::EMPTY),
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, column: (usize, usize)) {
+        match self {
+            Self::Dense {
+                min_x,
+                min_y,
+                width,
+                columns,
+            } => columns[(y - *min_y) * *width + (x - *min_x)] = column,
+            Self::Sparse(columns) => {
+                columns.insert((x, y), column);
+            }
+        }
+    }
+}
+
+/// Bricks after settling, in the order they came to rest.
+#[derive(Debug, Clone)]
+struct Stack {
+    positions: Vec<(Point, Point)>,
+    adj: Vec<Vec<usize>>,
+    in_deg: Vec<usize>,
+}
+
+fn settle(bricks: &[(Point, Point, usize)]) -> Stack {
+    let mut height_map = HeightMap::for_bricks(bricks);
+    let mut positions = Vec::new();
     let mut adj: Vec<Vec<_>> = Vec::new();
     let mut in_deg = Vec::new();
-    for &(from, to) in &bricks {
+    for &(from, to, _) in bricks {
         let blocks = iproduct!(from.x..=to.x, from.y..=to.y, from.z..=to.z)
             .map(|(x, y, z)| Point { x, y, z });
 
         let base_height = blocks
             .clone()
-            .map(|p| height_map[p.x][p.y].0 + 1)
+            .map(|p| height_map.get(p.x, p.y).0 + 1)
             .max()
             .unwrap_or(0);
 
@@ -69,26 +128,46 @@ fn main() -> Result<()> {
         for p in blocks {
             let final_z = p.z - from.z + base_height;
             // blocks are ordered by ascending z
-            debug_assert!(height_map[p.x][p.y].0 < final_z);
+            let (height, below) = height_map.get(p.x, p.y);
+            debug_assert!(height < final_z);
 
-            if height_map[p.x][p.y].0 == final_z - 1 {
-                let below = height_map[p.x][p.y].1;
-                if below != v && below != usize::MAX && adj[below].last() != Some(&v) {
-                    adj[below].push(v);
-                    in_deg_v += 1;
-                }
+            if height == final_z - 1
+                && below != v
+                && below != usize::MAX
+                && adj[below].last() != Some(&v)
+            {
+                adj[below].push(v);
+                in_deg_v += 1;
             }
 
-            height_map[p.x][p.y] = (final_z, v);
+            height_map.set(p.x, p.y, (final_z, v));
         }
 
         adj.push(Vec::new());
         in_deg.push(in_deg_v);
+        positions.push((
+            Point {
+                z: base_height,
+                ..from
+            },
+            Point {
+                z: to.z - from.z + base_height,
+                ..to
+            },
+        ));
     }
 
+    Stack {
+        positions,
+        adj,
+        in_deg,
+    }
+}
+
+fn fall_counts_bfs(adj: &[Vec<usize>], in_deg: &[usize]) -> Vec<usize> {
     let mut rem_in_deg = vec![(0, 0); adj.len()];
     let mut queue = VecDeque::new();
-    let (part1, part2) = (0..adj.len())
+    (0..adj.len())
         .map(|v0| {
             queue.push_back(v0);
             let mut count = 0;
@@ -108,9 +187,276 @@ fn main() -> Result<()> {
 
             count - 1
         })
-        .fold((0, 0), |(part1, part2), cnt| {
-            (part1 + usize::from(cnt == 0), part2 + cnt)
-        });
+        .collect()
+}
+
+/// Dominator tree of the support graph, rooted at a virtual ground node.
+///
+/// Removing a brick makes exactly the bricks in its subtree fall.
+#[derive(Debug, Clone)]
+struct DominatorTree {
+    jumps: Vec<Vec<usize>>,
+    depth: Vec<usize>,
+}
+
+impl DominatorTree {
+    /// Builds the tree from the support graph, whose nodes must be topologically ordered.
+    fn new(adj: &[Vec<usize>]) -> Self {
+        let ground = adj.len();
+        let mut supports = vec![Vec::new(); adj.len()];
+        for (v, row) in adj.iter().enumerate() {
+            for &v2 in row {
+                debug_assert!(v < v2);
+                supports[v2].push(v);
+            }
+        }
+
+        let mut tree = Self {
+            jumps: vec![Vec::new(); adj.len() + 1],
+            depth: vec![0; adj.len() + 1],
+        };
+        for (v, supports_v) in supports.iter().enumerate() {
+            let idom = supports_v
+                .iter()
+                .copied()
+                .reduce(|v1, v2| tree.lca(v1, v2))
+                .unwrap_or(ground);
+
+            tree.depth[v] = tree.depth[idom] + 1;
+            let mut jump = idom;
+            tree.jumps[v].push(jump);
+            while let Some(&next) = tree.jumps[jump].get(tree.jumps[v].len() - 1) {
+                jump = next;
+                tree.jumps[v].push(jump);
+            }
+        }
+
+        tree
+    }
+
+    fn ground(&self) -> usize {
+        self.depth.len() - 1
+    }
+
+    fn idom(&self, v: usize) -> Option<usize> {
+        self.jumps[v]
+            .first()
+            .copied()
+            .filter(|&v2| v2 != self.ground())
+    }
+
+    fn lca(&self, mut v1: usize, mut v2: usize) -> usize {
+        if self.depth[v1] < self.depth[v2] {
+            (v1, v2) = (v2, v1);
+        }
+        while self.depth[v1] > self.depth[v2] {
+            let diff = self.depth[v1] - self.depth[v2];
+            v1 = self.jumps[v1][diff.ilog2() as usize];
+        }
+
+        while v1 != v2 {
+            let k = (0..self.jumps[v1].len())
+                .rev()
+                .find(|&k| self.jumps[v1][k] != self.jumps[v2][k])
+                .unwrap_or(0);
+            v1 = self.jumps[v1][k];
+            v2 = self.jumps[v2][k];
+        }
+
+        v1
+    }
+
+    fn fall_counts(&self) -> Vec<usize> {
+        let n = self.ground();
+        let mut subtree_sizes = vec![1; n + 1];
+        for v in (0..n).rev() {
+            subtree_sizes[self.jumps[v][0]] += subtree_sizes[v];
+        }
+
+        subtree_sizes.truncate(n);
+        subtree_sizes.iter().map(|size| size - 1).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Algorithm {
+    Bfs,
+    #[default]
+    Dominators,
+}
+
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bfs" => Ok(Self::Bfs),
+            "dominators" => Ok(Self::Dominators),
+            _ => bail!("invalid algorithm: {}", s),
+        }
+    }
+}
+
+/// Maps a fall count to a color between blue (nothing falls) and red (the most falls).
+fn fall_color(count: usize, max_count: usize) -> [f64; 3] {
+    let t = count as f64 / max_count.max(1) as f64;
+    [t, 0.2, 1.0 - t]
+}
+
+/// Wavefront OBJ with one box per brick, colored through per-vertex colors.
+fn export_obj(positions: &[(Point, Point)], labels: &[usize], fall_counts: &[usize]) -> String {
+    let max_count = fall_counts.iter().copied().max().unwrap_or(0);
+    let mut obj = String::new();
+    for (i, ((from, to), &count)) in positions.iter().zip(fall_counts).enumerate() {
+        let [r, g, b] = fall_color(count, max_count);
+        writeln!(obj, "o brick_{}_falls_{count}", labels[i]).unwrap();
+        for (x, y, z) in iproduct!([from.x, to.x + 1], [from.y, to.y + 1], [from.z, to.z + 1]) {
+            writeln!(obj, "v {x} {y} {z} {r:.3} {g:.3} {b:.3}").unwrap();
+        }
+
+        // Corners are numbered by their (x, y, z) bits, in that order of significance.
+        let base = 8 * i + 1;
+        for face in [
+            [0, 1, 3, 2],
+            [4, 6, 7, 5],
+            [0, 4, 5, 1],
+            [2, 3, 7, 6],
+            [0, 2, 6, 4],
+            [1, 5, 7, 3],
+        ] {
+            let [a, b, c, d] = face.map(|corner| base + corner);
+            writeln!(obj, "f {a} {b} {c} {d}").unwrap();
+        }
+    }
+
+    obj
+}
+
+/// One line per unit cube: `x y z brick fall_count`.
+fn export_voxels(positions: &[(Point, Point)], labels: &[usize], fall_counts: &[usize]) -> String {
+    let mut voxels = String::new();
+    for (i, ((from, to), &count)) in positions.iter().zip(fall_counts).enumerate() {
+        for (x, y, z) in iproduct!(from.x..=to.x, from.y..=to.y, from.z..=to.z) {
+            writeln!(voxels, "{x} {y} {z} {} {count}", labels[i]).unwrap();
+        }
+    }
+
+    voxels
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Export {
+    Obj,
+    Voxels,
+}
+
+impl FromStr for Export {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "obj" => Ok(Self::Obj),
+            "voxels" => Ok(Self::Voxels),
+            _ => bail!("invalid export format: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Options {
+    algorithm: Algorithm,
+    holders_of: Option<usize>,
+    export: Option<Export>,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--algorithm" => options.algorithm = value.parse()?,
+                "--holders" => options.holders_of = Some(value.parse().context("invalid brick")?),
+                "--export" => options.export = Some(value.parse()?),
+                _ => bail!(
+                    "usage: day22 [--algorithm bfs|dominators] [--holders BRICK] \
+                     [--export obj|voxels]"
+                ),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn main() -> Result<()> {
+    let options = Options::from_args()?;
+    let mut bricks: Vec<_> = io::stdin()
+        .lock()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line?;
+            let (from, to) = line.split_once('~').context("invalid line")?;
+            let from = Point::from_str(from)?;
+            let to = Point::from_str(to)?;
+            ensure!(
+                from.x <= to.x && from.y <= to.y && from.z <= to.z,
+                "coordinates out of order"
+            );
+            Ok((from, to, i))
+        })
+        .try_collect()?;
+    bricks.sort_unstable_by_key(|(from, _, _)| from.z);
+
+    let Stack {
+        positions,
+        adj,
+        in_deg,
+    } = settle(&bricks);
+
+    let tree = (options.algorithm == Algorithm::Dominators || options.holders_of.is_some())
+        .then(|| DominatorTree::new(&adj));
+    let fall_counts = match (options.algorithm, &tree) {
+        (Algorithm::Dominators, Some(tree)) => tree.fall_counts(),
+        _ => fall_counts_bfs(&adj, &in_deg),
+    };
+    if let Some(export) = options.export {
+        let labels = bricks.iter().map(|&(_, _, i)| i).collect_vec();
+        match export {
+            Export::Obj => print!("{}", export_obj(&positions, &labels, &fall_counts)),
+            Export::Voxels => print!("{}", export_voxels(&positions, &labels, &fall_counts)),
+        }
+        return Ok(());
+    }
+
+    let (part1, part2) = fall_counts.into_iter().fold((0, 0), |(part1, part2), cnt| {
+        (part1 + usize::from(cnt == 0), part2 + cnt)
+    });
+
+    if let (Some(brick), Some(tree)) = (options.holders_of, &tree) {
+        let mut v = bricks
+            .iter()
+            .position(|&(_, _, i)| i == brick)
+            .context("no such brick")?;
+        let mut holders = Vec::new();
+        while let Some(idom) = tree.idom(v) {
+            holders.push(bricks[idom].2);
+            v = idom;
+        }
+
+        if holders.is_empty() {
+            println!("Brick {brick} is held up by the ground only");
+        } else {
+            println!(
+                "Brick {brick} is held up by bricks {}",
+                holders.iter().join(", ")
+            );
+        }
+    }
 
     println!("Part 1: {part1}");
     println!("Part 2: {part2}");
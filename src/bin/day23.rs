@@ -1,31 +1,169 @@
-use std::{io, iter, str::FromStr};
+use std::{
+    cmp::Reverse,
+    fmt::Write,
+    io, iter,
+    num::NonZeroUsize,
+    ops::{BitAnd, BitOr, Not},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use aoc2023::log;
 use itertools::{iproduct, Itertools};
+use rustc_hash::{FxHashMap, FxHashSet};
 
-type Bitset = u128;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    BruteForce,
+    Pruned,
+}
+
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "brute-force" => Ok(Self::BruteForce),
+            "pruned" => Ok(Self::Pruned),
+            _ => bail!("invalid algorithm: {}", s),
+        }
+    }
+}
+
+trait Bitset:
+    Copy
+    + Default
+    + Eq
+    + Send
+    + Sync
+    + BitOr<Output = Self>
+    + BitAnd<Output = Self>
+    + Not<Output = Self>
+{
+    const CAPACITY: usize;
+
+    fn singleton(v: usize) -> Self;
+
+    fn pop_first(&mut self) -> Option<usize>;
+
+    fn contains(self, v: usize) -> bool {
+        (self & Self::singleton(v)) != Self::default()
+    }
+}
+
+impl Bitset for u128 {
+    const CAPACITY: usize = Self::BITS as usize;
+
+    fn singleton(v: usize) -> Self {
+        1 << v
+    }
+
+    fn pop_first(&mut self) -> Option<usize> {
+        self.checked_sub(1).map(|self_minus_one| {
+            let v = self.trailing_zeros() as usize;
+            *self &= self_minus_one;
+            v
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Chunks<const N: usize>([u64; N]);
+
+impl<const N: usize> Default for Chunks<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> BitOr for Chunks<N> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] | rhs.0[i]))
+    }
+}
+
+impl<const N: usize> BitAnd for Chunks<N> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] & rhs.0[i]))
+    }
+}
+
+impl<const N: usize> Not for Chunks<N> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0.map(|chunk| !chunk))
+    }
+}
+
+impl<const N: usize> Bitset for Chunks<N> {
+    const CAPACITY: usize = N * u64::BITS as usize;
+
+    fn singleton(v: usize) -> Self {
+        let mut chunks = [0; N];
+        chunks[v / 64] = 1 << (v % 64);
+        Self(chunks)
+    }
+
+    fn pop_first(&mut self) -> Option<usize> {
+        self.0.iter_mut().enumerate().find_map(|(i, chunk)| {
+            chunk.checked_sub(1).map(|chunk_minus_one| {
+                let v = 64 * i + chunk.trailing_zeros() as usize;
+                *chunk &= chunk_minus_one;
+                v
+            })
+        })
+    }
+}
 
 #[derive(Debug, Clone)]
 struct Graph {
     num_nodes: usize,
-    adj: [Bitset; Self::MAX_SIZE],
-    node_weights: [u16; Self::MAX_SIZE],
+    edges: Vec<(usize, usize)>,
+    slopes: Vec<(usize, usize)>,
+    node_weights: Vec<u32>,
+    node_cells: Vec<Vec<(usize, usize)>>,
     start: usize,
     target: usize,
 }
 
 impl Graph {
-    const MAX_SIZE: usize = Bitset::BITS as usize;
+    fn with_bitsets<B: Bitset>(&self, undirected: bool) -> Result<BitGraph<'_, B>> {
+        ensure!(
+            self.num_nodes <= B::CAPACITY,
+            "graph has {} nodes, but the bitset only supports {}",
+            self.num_nodes,
+            B::CAPACITY
+        );
+
+        let mut adj = vec![B::default(); self.num_nodes];
+        for &(v, v2) in &self.edges {
+            adj[v] = adj[v] | B::singleton(v2);
+            if undirected {
+                adj[v2] = adj[v2] | B::singleton(v);
+            }
+        }
 
-    fn neighbors(&self, v: usize, mask: Bitset) -> impl Iterator<Item = usize> {
+        Ok(BitGraph { graph: self, adj })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BitGraph<'a, B> {
+    graph: &'a Graph,
+    adj: Vec<B>,
+}
+
+impl<B: Bitset> BitGraph<'_, B> {
+    fn neighbors(&self, v: usize, mask: B) -> impl Iterator<Item = usize> {
         let mut bs = self.adj[v] & mask;
-        iter::from_fn(move || {
-            bs.checked_sub(1).map(|bs_minus_one| {
-                let v2 = bs.trailing_zeros() as usize;
-                bs &= bs_minus_one;
-                v2
-            })
-        })
+        iter::from_fn(move || bs.pop_first())
     }
 }
 
@@ -35,11 +173,13 @@ impl FromStr for Graph {
     fn from_str(s: &str) -> Result<Self> {
         let grid: Vec<_> = s.lines().map(|line| line.as_bytes()).collect();
         let height = grid.len();
-        let width = grid[0].len();
+        let width = grid.first().context("empty grid")?.len();
 
         let mut num_nodes = 0;
-        let mut adj = [Bitset::default(); Self::MAX_SIZE];
-        let mut node_weights = [0; Self::MAX_SIZE];
+        let mut edges = Vec::new();
+        let mut slopes = Vec::new();
+        let mut node_weights = Vec::new();
+        let mut node_cells = Vec::new();
         let mut endpoints = vec![vec![None; width]; height];
         for (y, x) in iproduct!(0..height, 0..width) {
             if grid[y][x] != b'.' || endpoints[y][x].is_some() {
@@ -53,23 +193,25 @@ impl FromStr for Graph {
             };
 
             if let Some(mut cur) = maybe_only_neighbor {
-                let mut len = 2;
+                let mut cells = vec![(y, x), cur];
                 let mut prev = (y, x);
                 while let Ok((next_y, next_x, _)) = grid_neighbors(cur.0, cur.1, &grid)
                     .filter(|&(y, x, c)| (y, x) != prev && c == b'.')
                     .exactly_one()
                 {
-                    len += 1;
                     prev = cur;
                     cur = (next_y, next_x);
+                    cells.push(cur);
                 }
 
                 endpoints[y][x] = Some(num_nodes);
                 endpoints[cur.0][cur.1] = Some(num_nodes);
-                node_weights[num_nodes] = len;
+                node_weights.push(cells.len() as u32);
+                node_cells.push(cells);
             } else {
                 endpoints[y][x] = Some(num_nodes);
-                node_weights[num_nodes] = 1;
+                node_weights.push(1);
+                node_cells.push(vec![(y, x)]);
             }
 
             num_nodes += 1;
@@ -82,20 +224,22 @@ impl FromStr for Graph {
                     let v_right =
                         endpoints[y][x + 1].context("slope does not connect endpoints")?;
                     if grid[y][x] == b'>' {
-                        adj[v_left] |= 1 << v_right;
+                        edges.push((v_left, v_right));
                     } else {
-                        adj[v_right] |= 1 << v_left;
+                        edges.push((v_right, v_left));
                     }
+                    slopes.push((y, x));
                 }
                 b'^' | b'v' => {
                     let v_top = endpoints[y - 1][x].context("slope does not connect endpoints")?;
                     let v_bottom =
                         endpoints[y + 1][x].context("slope does not connect endpoints")?;
                     if grid[y][x] == b'v' {
-                        adj[v_top] |= 1 << v_bottom;
+                        edges.push((v_top, v_bottom));
                     } else {
-                        adj[v_bottom] |= 1 << v_top;
+                        edges.push((v_bottom, v_top));
                     }
+                    slopes.push((y, x));
                 }
                 _ => {}
             }
@@ -110,8 +254,10 @@ impl FromStr for Graph {
 
         Ok(Self {
             num_nodes,
-            adj,
+            edges,
+            slopes,
             node_weights,
+            node_cells,
             start,
             target,
         })
@@ -133,11 +279,11 @@ fn grid_neighbors<'a>(
     .filter_map(|(y, x)| Some((y, x, *grid.get(y)?.get(x)?)))
 }
 
-fn dag_dfs(v: usize, graph: &Graph, longest_path: &mut [u16]) -> u16 {
-    if longest_path[v] == u16::MAX {
+fn dag_dfs<B: Bitset>(v: usize, graph: &BitGraph<B>, longest_path: &mut [u32]) -> u32 {
+    if longest_path[v] == u32::MAX {
         longest_path[v] = graph
-            .neighbors(v, u128::MAX)
-            .map(|v2| dag_dfs(v2, graph, longest_path) + 1 + graph.node_weights[v])
+            .neighbors(v, !B::default())
+            .map(|v2| dag_dfs(v2, graph, longest_path) + 1 + graph.graph.node_weights[v])
             .max()
             .unwrap_or(0);
     }
@@ -145,39 +291,430 @@ fn dag_dfs(v: usize, graph: &Graph, longest_path: &mut [u16]) -> u16 {
     longest_path[v]
 }
 
-fn longest_path_brute_force(v: usize, len: u16, graph: &Graph, seen: Bitset) -> u16 {
-    if v == graph.target {
-        return len + graph.node_weights[v];
+fn longest_path_brute_force<B: Bitset>(v: usize, len: u32, graph: &BitGraph<B>, seen: B) -> u32 {
+    if v == graph.graph.target {
+        return len + graph.graph.node_weights[v];
+    }
+
+    graph
+        .neighbors(v, !seen)
+        .map(|v2| {
+            let len = len + graph.graph.node_weights[v] + 1;
+            longest_path_brute_force(v2, len, graph, seen | B::singleton(v))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn split_subtrees<B: Bitset>(
+    v: usize,
+    len: u32,
+    graph: &BitGraph<B>,
+    seen: B,
+    depth: usize,
+    subtrees: &mut Vec<(usize, u32, B)>,
+) -> u32 {
+    if v == graph.graph.target {
+        return len + graph.graph.node_weights[v];
+    }
+    if depth == 0 {
+        subtrees.push((v, len, seen));
+        return 0;
     }
 
-    let ans = graph
+    graph
         .neighbors(v, !seen)
         .map(|v2| {
-            longest_path_brute_force(v2, len + graph.node_weights[v] + 1, graph, seen | (1 << v))
+            let len = len + graph.graph.node_weights[v] + 1;
+            split_subtrees(v2, len, graph, seen | B::singleton(v), depth - 1, subtrees)
         })
         .max()
-        .unwrap_or(0);
+        .unwrap_or(0)
+}
+
+fn longest_path_parallel<B: Bitset>(
+    graph: &BitGraph<B>,
+    threads: NonZeroUsize,
+    split_depth: usize,
+) -> u32 {
+    let mut subtrees = Vec::new();
+    let shallow = split_subtrees(
+        graph.graph.start,
+        0,
+        graph,
+        B::default(),
+        split_depth,
+        &mut subtrees,
+    );
+
+    let next_subtree = AtomicUsize::new(0);
+    let deep = thread::scope(|scope| {
+        let workers = (0..threads.get())
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut best = 0;
+                    while let Some(&(v, len, seen)) =
+                        subtrees.get(next_subtree.fetch_add(1, Ordering::Relaxed))
+                    {
+                        best = best.max(longest_path_brute_force(v, len, graph, seen));
+                    }
+                    best
+                })
+            })
+            .collect_vec();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("worker thread panicked"))
+            .max()
+            .unwrap_or(0)
+    });
+
+    shallow.max(deep)
+}
+
+/// Search over the junctions of the undirected graph, with corridors (nodes with
+/// exactly two neighbors) contracted into weighted edges.
+#[derive(Debug)]
+struct PrunedSearch<B> {
+    junctions: Vec<usize>,
+    node_values: Vec<u32>,
+    adj: Vec<Vec<(usize, u32, Vec<usize>)>>,
+    adj_masks: Vec<B>,
+    start: usize,
+    exit: usize,
+    exit_tail: u32,
+    target: usize,
+    best: u32,
+    path: Vec<(usize, usize)>,
+    best_path: Vec<(usize, usize)>,
+    stack: Vec<usize>,
+}
+
+impl<B: Bitset> PrunedSearch<B> {
+    fn new(graph: &BitGraph<B>) -> Self {
+        let Graph {
+            num_nodes,
+            ref node_weights,
+            start,
+            target,
+            ..
+        } = *graph.graph;
+
+        // If the target is a dead end, its only neighbor must lead straight to it.
+        let (exit, exit_tail) = match graph.neighbors(target, !B::default()).exactly_one() {
+            Ok(v) => (v, node_weights[target] + 1),
+            Err(_) => (target, 0),
+        };
+
+        let junctions = (0..num_nodes)
+            .filter(|&v| {
+                let is_corridor = graph.neighbors(v, !B::default()).count() == 2;
+                v == start || v == exit || (v != target && !is_corridor)
+            })
+            .collect_vec();
+        let mut junction_index = vec![usize::MAX; num_nodes];
+        for (i, &v) in junctions.iter().enumerate() {
+            junction_index[v] = i;
+        }
+
+        let mut adj: Vec<Vec<(usize, u32, Vec<usize>)>> = vec![Vec::new(); junctions.len()];
+        let mut adj_masks = vec![B::default(); junctions.len()];
+        for (i, &v) in junctions.iter().enumerate() {
+            for mut cur in graph.neighbors(v, !B::default()) {
+                let mut prev = v;
+                let mut value = 0;
+                let mut chain = Vec::new();
+                while cur != target && junction_index[cur] == usize::MAX {
+                    value += node_weights[cur] + 1;
+                    chain.push(cur);
+                    let Some(next) = graph.neighbors(cur, !B::singleton(prev)).next() else {
+                        break;
+                    };
+                    (prev, cur) = (cur, next);
+                }
+
+                let j = junction_index[cur];
+                if j == usize::MAX || j == i {
+                    continue;
+                }
+                if let Some(edge) = adj[i].iter_mut().find(|edge| edge.0 == j) {
+                    if value > edge.1 {
+                        *edge = (j, value, chain);
+                    }
+                } else {
+                    adj[i].push((j, value, chain));
+                    adj_masks[i] = adj_masks[i] | B::singleton(j);
+                }
+            }
+
+            adj[i].sort_unstable_by_key(|&(_, value, _)| Reverse(value));
+        }
+
+        Self {
+            node_values: junctions.iter().map(|&v| node_weights[v] + 1).collect(),
+            junctions,
+            adj,
+            adj_masks,
+            start: junction_index[start],
+            exit: junction_index[exit],
+            exit_tail,
+            target,
+            best: 0,
+            path: Vec::new(),
+            best_path: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Bounds the value of any path from `v` to the exit avoiding `seen`.
+    ///
+    /// A junction has at most two edges on the path, so charging each junction
+    /// half of its two heaviest edges covers all edge values.
+    fn upper_bound(&mut self, v: usize, seen: B) -> Option<u32> {
+        let mut reach = B::singleton(v);
+        let mut bound = self.exit_tail;
+        self.stack.push(v);
+        while let Some(v2) = self.stack.pop() {
+            let mut bs = self.adj_masks[v2] & !(seen | reach);
+            while let Some(v3) = bs.pop_first() {
+                reach = reach | B::singleton(v3);
+                bound += self.node_values[v3];
+                self.stack.push(v3);
+            }
+        }
+        if !reach.contains(self.exit) {
+            return None;
+        }
+
+        let mut double_edges = 0;
+        let mut remaining = reach;
+        while let Some(v2) = remaining.pop_first() {
+            let mut edges = self.adj[v2].iter().filter(|&&(v3, ..)| reach.contains(v3));
+            double_edges += edges.next().map_or(0, |e| e.1) + edges.next().map_or(0, |e| e.1);
+        }
+
+        Some(bound + double_edges / 2)
+    }
+
+    fn dfs(&mut self, v: usize, len: u32, seen: B) {
+        let len = len + self.node_values[v];
+        if v == self.exit {
+            if len + self.exit_tail > self.best {
+                self.best = len + self.exit_tail;
+                self.best_path.clone_from(&self.path);
+            }
+            return;
+        }
+
+        let seen = seen | B::singleton(v);
+        match self.upper_bound(v, seen) {
+            Some(bound) if len + bound > self.best => {}
+            _ => return,
+        }
+
+        for i in 0..self.adj[v].len() {
+            let &(v2, value, _) = &self.adj[v][i];
+            if !seen.contains(v2) {
+                self.path.push((v, i));
+                self.dfs(v2, len + value, seen);
+                self.path.pop();
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        self.dfs(self.start, 0, B::default());
+    }
+
+    /// Expands the best path found into the nodes of the original graph.
+    fn best_hike(&self) -> Vec<usize> {
+        let mut hike = Vec::new();
+        for &(v, edge_idx) in &self.best_path {
+            hike.push(self.junctions[v]);
+            hike.extend(&self.adj[v][edge_idx].2);
+        }
+        hike.push(self.junctions[self.exit]);
+        if self.junctions[self.exit] != self.target {
+            hike.push(self.target);
+        }
+        hike
+    }
+}
+
+fn longest_path_pruned<B: Bitset>(graph: &BitGraph<B>) -> u32 {
+    let mut search = PrunedSearch::new(graph);
+    search.run();
+    search.best - 1
+}
 
-    ans
+fn longest_hike<B: Bitset>(graph: &Graph) -> Result<Vec<usize>> {
+    let mut search = PrunedSearch::new(&graph.with_bitsets::<B>(true)?);
+    search.run();
+    Ok(search.best_hike())
+}
+
+fn render_dot(graph: &Graph, hike: &[usize]) -> String {
+    let on_hike: FxHashSet<_> = hike
+        .iter()
+        .tuple_windows()
+        .flat_map(|(&v, &v2)| [(v, v2), (v2, v)])
+        .collect();
+
+    let mut dot = String::from("digraph {\n");
+    for v in 0..graph.num_nodes {
+        let style = if v == graph.start {
+            ", style=filled, fillcolor=palegreen"
+        } else if v == graph.target {
+            ", style=filled, fillcolor=lightcoral"
+        } else {
+            ""
+        };
+        let weight = graph.node_weights[v];
+        writeln!(dot, "    {v} [label=\"{v}\\n{weight}\"{style}];").unwrap();
+    }
+    for &(v, v2) in &graph.edges {
+        let style = if on_hike.contains(&(v, v2)) {
+            " [color=red, penwidth=2]"
+        } else {
+            ""
+        };
+        writeln!(dot, "    {v} -> {v2}{style};").unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn render_hike(input: &str, graph: &Graph, hike: &[usize]) -> String {
+    let mut grid = input
+        .lines()
+        .map(|line| line.as_bytes().to_vec())
+        .collect_vec();
+    let slopes: FxHashMap<_, _> = graph
+        .edges
+        .iter()
+        .zip(&graph.slopes)
+        .flat_map(|(&(v, v2), &cell)| [((v, v2), cell), ((v2, v), cell)])
+        .collect();
+
+    let hike_cells = hike
+        .iter()
+        .tuple_windows()
+        .filter_map(|(&v, &v2)| slopes.get(&(v, v2)).copied())
+        .chain(
+            hike.iter()
+                .flat_map(|&v| graph.node_cells[v].iter().copied()),
+        );
+    for (y, x) in hike_cells {
+        grid[y][x] = if y == 0 { b'S' } else { b'O' };
+    }
+
+    grid.into_iter()
+        .map(|row| String::from_utf8_lossy(&row).into_owned() + "\n")
+        .collect()
+}
+
+fn solve<B: Bitset>(graph: &Graph, options: &Options) -> Result<(u32, u32, Option<Vec<usize>>)> {
+    let graph_directed = graph.with_bitsets::<B>(false)?;
+    let mut longest_path = vec![u32::MAX; graph.num_nodes];
+    longest_path[graph.target] = graph.node_weights[graph.target];
+    let part1 = dag_dfs(graph.start, &graph_directed, &mut longest_path) - 1;
+
+    let graph_undirected = graph.with_bitsets::<B>(true)?;
+    let part2 = match options.algorithm {
+        Algorithm::BruteForce if options.threads.get() > 1 => {
+            longest_path_parallel(&graph_undirected, options.threads, options.split_depth)
+        }
+        Algorithm::BruteForce => {
+            longest_path_brute_force(graph.start, 0, &graph_undirected, B::default())
+        }
+        Algorithm::Pruned => longest_path_pruned(&graph_undirected),
+    } - 1;
+
+    let hike = options
+        .render
+        .map(|_| longest_hike::<B>(graph))
+        .transpose()?;
+    Ok((part1, part2, hike))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Render {
+    Dot,
+    Hike,
+}
+
+impl FromStr for Render {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "hike" => Ok(Self::Hike),
+            _ => bail!("invalid rendering: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Options {
+    algorithm: Algorithm,
+    threads: NonZeroUsize,
+    split_depth: usize,
+    render: Option<Render>,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self {
+            algorithm: Algorithm::Pruned,
+            threads: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            split_depth: 12,
+            render: None,
+        };
+
+        let mut args = log::args();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--algorithm" => options.algorithm = value.parse()?,
+                "--threads" => options.threads = value.parse().context("invalid thread count")?,
+                "--split-depth" => {
+                    options.split_depth = value.parse().context("invalid split depth")?;
+                }
+                "--render" => options.render = Some(value.parse()?),
+                _ => bail!(
+                    "usage: day23 [--algorithm brute-force|pruned] [--threads N] [--split-depth D] \
+                     [--render dot|hike]"
+                ),
+            }
+        }
+
+        Ok(options)
+    }
 }
 
 fn main() -> Result<()> {
+    let options = Options::from_args()?;
+
     let input = io::read_to_string(io::stdin().lock())?;
     let graph = Graph::from_str(&input)?;
 
-    let mut longest_path = vec![u16::MAX; graph.num_nodes];
-    longest_path[graph.target] = graph.node_weights[graph.target];
-    let part1 = dag_dfs(graph.start, &graph, &mut longest_path) - 1;
+    let (part1, part2, hike) = match graph.num_nodes {
+        0..=128 => solve::<u128>(&graph, &options)?,
+        129..=256 => solve::<Chunks<4>>(&graph, &options)?,
+        257..=512 => solve::<Chunks<8>>(&graph, &options)?,
+        _ => solve::<Chunks<16>>(&graph, &options)?,
+    };
 
-    let mut graph_undirected = graph.clone();
-    for v in 0..graph.num_nodes {
-        for v2 in graph.neighbors(v, u128::MAX) {
-            graph_undirected.adj[v2] |= 1 << v;
+    match (options.render, hike) {
+        (Some(Render::Dot), Some(hike)) => print!("{}", render_dot(&graph, &hike)),
+        (Some(Render::Hike), Some(hike)) => print!("{}", render_hike(&input, &graph, &hike)),
+        _ => {
+            println!("Part 1: {part1}");
+            println!("Part 2: {part2}");
         }
     }
-    let part2 = longest_path_brute_force(graph_undirected.start, 0, &graph_undirected, 0) - 1;
-
-    println!("Part 1: {part1}");
-    println!("Part 2: {part2}");
     Ok(())
 }
@@ -1,7 +1,9 @@
 use std::{collections::VecDeque, io};
 
-use anyhow::{Context, Result};
-use rand::Rng;
+use anyhow::{bail, Context, Result};
+use aoc2023::log;
+use itertools::Itertools;
+use rand::seq::SliceRandom;
 use rustc_hash::FxHashMap;
 
 #[derive(Debug, Clone, Default)]
@@ -13,92 +15,252 @@ impl<'a> LabelCompression<'a> {
         *self.0.entry(label).or_insert(next)
     }
 
+    fn find(&self, label: &str) -> Option<usize> {
+        self.0.get(label).copied()
+    }
+
     fn len(&self) -> usize {
         self.0.len()
     }
-}
 
-fn run_bfs(
-    adj: &[Vec<(usize, usize)>],
-    queue: &mut VecDeque<usize>,
-    inc: &mut [(usize, usize)],
-    s: usize,
-) {
-    queue.push_back(s);
-    inc.fill((usize::MAX, usize::MAX));
-    inc[s] = (s, usize::MAX);
-    while let Some(v) = queue.pop_front() {
-        for (edge_idx, &(v2, _)) in adj[v].iter().enumerate() {
-            if v2 < adj.len() && inc[v2].0 == usize::MAX {
-                inc[v2] = (v, edge_idx);
-                queue.push_back(v2);
-            }
+    fn names(&self) -> Vec<&'a str> {
+        let mut names = vec![""; self.len()];
+        for (&label, &v) in &self.0 {
+            names[v] = label;
         }
+        names
     }
 }
 
-fn main() -> Result<()> {
-    let input = io::read_to_string(io::stdin().lock())?;
-    let mut labels = LabelCompression::default();
-    let mut adj = Vec::new();
-    for line in input.lines() {
-        let (label, neighbors) = line.split_once(": ").context("invalid input")?;
-        let v = labels.get(label);
-        for label2 in neighbors.split_ascii_whitespace() {
-            let v2 = labels.get(label2);
-            adj.resize_with(labels.len(), Vec::new);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    CutSize(usize),
+    Global,
+    Between(usize, usize),
+}
 
-            assert_ne!(v, v2);
-            let v_idx = adj[v].len();
-            let v2_idx = adj[v2].len();
-            adj[v].push((v2, v2_idx));
-            adj[v2].push((v, v_idx));
-        }
+/// A cut of the graph, given by the side of each node relative to the source.
+#[derive(Debug, Clone)]
+struct Cut {
+    value: usize,
+    source_side: Vec<bool>,
+}
+
+/// Undirected graph with unit edge capacities.
+///
+/// Each edge is stored in both endpoints' lists together with the index of the
+/// reverse entry. Saturated entries have their target negated so that they can
+/// be skipped by the BFS and restored by [`Self::reset_flow`].
+#[derive(Debug, Clone, Default)]
+struct FlowGraph {
+    adj: Vec<Vec<(usize, usize)>>,
+    queue: VecDeque<usize>,
+    inc: Vec<(usize, usize)>,
+}
+
+impl FlowGraph {
+    fn num_nodes(&self) -> usize {
+        self.adj.len()
     }
-    let n = adj.len();
 
-    const MIN_CUT_VALUE: usize = 3;
-    let mut rng = rand::thread_rng();
-    let mut queue = VecDeque::new();
-    let mut inc = vec![(0, 0); adj.len()];
-    let part1 = 'outer: loop {
-        for row in &mut adj {
+    fn add_edge(&mut self, v: usize, v2: usize) {
+        assert_ne!(v, v2);
+        let n = self.adj.len().max(v + 1).max(v2 + 1);
+        self.adj.resize_with(n, Vec::new);
+        self.inc.resize(n, (0, 0));
+
+        let v_idx = self.adj[v].len();
+        let v2_idx = self.adj[v2].len();
+        self.adj[v].push((v2, v2_idx));
+        self.adj[v2].push((v, v_idx));
+    }
+
+    fn reset_flow(&mut self) {
+        let n = self.num_nodes();
+        for row in &mut self.adj {
             for (v, _) in row {
                 if *v >= n {
                     *v = v.wrapping_neg();
                 }
             }
         }
+    }
+
+    fn run_bfs(&mut self, s: usize) {
+        let n = self.num_nodes();
+        self.queue.push_back(s);
+        self.inc.fill((usize::MAX, usize::MAX));
+        self.inc[s] = (s, usize::MAX);
+        while let Some(v) = self.queue.pop_front() {
+            for (edge_idx, &(v2, _)) in self.adj[v].iter().enumerate() {
+                if v2 < n && self.inc[v2].0 == usize::MAX {
+                    self.inc[v2] = (v, edge_idx);
+                    self.queue.push_back(v2);
+                }
+            }
+        }
+    }
 
-        let s = 0;
-        let t = rng.gen_range(0..n);
-        for _ in 0..MIN_CUT_VALUE {
-            run_bfs(&adj, &mut queue, &mut inc, s);
-            if inc[t].0 == usize::MAX {
-                continue 'outer;
+    fn augment(&mut self, s: usize, t: usize) {
+        let mut v = t;
+        while v != s {
+            let (from, from_idx) = self.inc[v];
+            let (to, to_idx) = self.adj[from][from_idx];
+            if self.adj[to][to_idx].0 == from {
+                self.adj[from][from_idx] = (to.wrapping_neg(), to_idx);
+            } else {
+                self.adj[to][to_idx] = (from, from_idx);
             }
 
-            let mut v = t;
-            while v != s {
-                let (from, from_idx) = inc[v];
-                let (to, to_idx) = adj[from][from_idx];
-                if adj[to][to_idx].0 == from {
-                    adj[from][from_idx] = (to.wrapping_neg(), to_idx);
-                } else {
-                    adj[to][to_idx] = (from, from_idx);
+            v = from;
+        }
+    }
+
+    /// Finds a minimum `s`-`t` cut, giving up once the flow exceeds `limit`.
+    fn min_cut(&mut self, s: usize, t: usize, limit: usize) -> Option<Cut> {
+        assert_ne!(s, t);
+        self.reset_flow();
+        let mut value = 0;
+        loop {
+            self.run_bfs(s);
+            if self.inc[t].0 == usize::MAX {
+                break;
+            }
+            if value == limit {
+                return None;
+            }
+
+            self.augment(s, t);
+            value += 1;
+        }
+
+        let source_side = self.inc.iter().map(|&(v, _)| v != usize::MAX).collect();
+        Some(Cut { value, source_side })
+    }
+
+    fn global_min_cut(&mut self) -> Option<Cut> {
+        let mut best: Option<Cut> = None;
+        for t in 1..self.num_nodes() {
+            let limit = best.as_ref().map_or(usize::MAX, |cut| cut.value - 1);
+            if let Some(cut) = self.min_cut(0, t, limit) {
+                let is_zero = cut.value == 0;
+                best = Some(cut);
+                if is_zero {
+                    break;
                 }
+            }
+        }
+
+        best
+    }
+
+    /// Finds a minimum `s`-`t` cut of exactly `k` edges, trying all `t` in random order.
+    fn cut_of_size(&mut self, k: usize) -> Option<Cut> {
+        let mut targets = (1..self.num_nodes()).collect_vec();
+        targets.shuffle(&mut rand::thread_rng());
+        targets
+            .into_iter()
+            .find_map(|t| self.min_cut(0, t, k).filter(|cut| cut.value == k))
+    }
+
+    fn cut_edges(&self, cut: &Cut) -> Vec<(usize, usize)> {
+        let n = self.num_nodes();
+        (0..n)
+            .filter(|&v| cut.source_side[v])
+            .flat_map(|v| self.adj[v].iter().map(move |&(v2, _)| (v, v2)))
+            .map(|(v, v2)| (v, if v2 >= n { v2.wrapping_neg() } else { v2 }))
+            .filter(|&(_, v2)| !cut.source_side[v2])
+            .collect()
+    }
 
-                v = from;
+    /// Connected components of the graph after removing the edges of `cut`.
+    fn components(&self, cut: &Cut) -> Vec<Vec<usize>> {
+        let n = self.num_nodes();
+        let mut seen = vec![false; n];
+        let mut components = Vec::new();
+        for v0 in 0..n {
+            if seen[v0] {
+                continue;
             }
+
+            seen[v0] = true;
+            let mut component = vec![v0];
+            let mut i = 0;
+            while let Some(&v) = component.get(i) {
+                for &(v2, _) in &self.adj[v] {
+                    let v2 = if v2 >= n { v2.wrapping_neg() } else { v2 };
+                    if !seen[v2] && cut.source_side[v2] == cut.source_side[v] {
+                        seen[v2] = true;
+                        component.push(v2);
+                    }
+                }
+                i += 1;
+            }
+
+            components.push(component);
         }
 
-        run_bfs(&adj, &mut queue, &mut inc, s);
-        if inc[t].0 == usize::MAX {
-            let num_reachable = inc.iter().filter(|(v, _)| *v != usize::MAX).count();
-            break num_reachable * (n - num_reachable);
+        components
+    }
+}
+
+fn parse_mode(labels: &LabelCompression) -> Result<Mode> {
+    let args = log::args().collect_vec();
+    match args.iter().map(String::as_str).collect_vec()[..] {
+        [] => Ok(Mode::CutSize(3)),
+        ["--cut-size", k] => Ok(Mode::CutSize(k.parse().context("invalid cut size")?)),
+        ["--global"] => Ok(Mode::Global),
+        ["--between", s, t] => {
+            let s = labels
+                .find(s)
+                .with_context(|| format!("unknown node: {s}"))?;
+            let t = labels
+                .find(t)
+                .with_context(|| format!("unknown node: {t}"))?;
+            if s == t {
+                bail!("source and sink must differ");
+            }
+            Ok(Mode::Between(s, t))
         }
-    };
+        _ => bail!("usage: day25 [--cut-size K | --global | --between S T]"),
+    }
+}
 
+fn main() -> Result<()> {
+    let input = io::read_to_string(io::stdin().lock())?;
+    let mut labels = LabelCompression::default();
+    let mut graph = FlowGraph::default();
+    for line in input.lines() {
+        let (label, neighbors) = line.split_once(": ").context("invalid input")?;
+        let v = labels.get(label);
+        for label2 in neighbors.split_ascii_whitespace() {
+            let v2 = labels.get(label2);
+            graph.add_edge(v, v2);
+        }
+    }
+
+    let mode = parse_mode(&labels)?;
+    let cut = match mode {
+        Mode::CutSize(k) => graph.cut_of_size(k),
+        Mode::Global => graph.global_min_cut(),
+        Mode::Between(s, t) => graph.min_cut(s, t, usize::MAX),
+    }
+    .context("no matching cut")?;
+
+    let components = graph.components(&cut);
+    let part1: usize = components.iter().map(Vec::len).product();
+
+    if mode != Mode::CutSize(3) {
+        let names = labels.names();
+        let edges = graph
+            .cut_edges(&cut)
+            .into_iter()
+            .map(|(v, v2)| format!("{}/{}", names[v], names[v2]))
+            .join(", ");
+        let sizes = components.iter().map(Vec::len).join(", ");
+        println!("Cut ({} edges): {edges}", cut.value);
+        println!("Components: {sizes}");
+    }
     println!("Part 1: {part1}");
     Ok(())
 }
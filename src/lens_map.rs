@@ -0,0 +1,263 @@
+use std::{
+    array,
+    fmt::{self, Display},
+    hash::Hash,
+};
+
+use rustc_hash::FxHashMap;
+
+/// The HASH algorithm from 2023 day 15.
+pub fn hash(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0_u8, |hash, &c| hash.wrapping_add(c).wrapping_mul(17))
+}
+
+#[derive(Debug, Clone)]
+struct LensBox<K, V> {
+    /// Entries in insertion order, with `None` left behind by removals.
+    slots: Vec<Option<(K, V)>>,
+    index: FxHashMap<K, usize>,
+}
+
+impl<K, V> Default for LensBox<K, V> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            index: FxHashMap::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> LensBox<K, V> {
+    fn compact(&mut self) {
+        self.slots.retain(Option::is_some);
+        for (i, (key, _)) in self.slots.iter().flatten().enumerate() {
+            *self.index.get_mut(key).unwrap() = i;
+        }
+    }
+}
+
+/// The HASHMAP from 2023 day 15: 256 boxes selected by [`hash`], each keeping its lenses in
+/// insertion order, with replacing a lens keeping its position.
+#[derive(Debug, Clone)]
+pub struct LensMap<K, V> {
+    boxes: [LensBox<K, V>; 256],
+    len: usize,
+}
+
+impl<K, V> Default for LensMap<K, V> {
+    fn default() -> Self {
+        Self {
+            boxes: array::from_fn(|_| LensBox::default()),
+            len: 0,
+        }
+    }
+}
+
+impl<K: AsRef<[u8]> + Hash + Eq + Clone, V> LensMap<K, V> {
+    pub fn box_of(key: &K) -> u8 {
+        hash(key.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let lens_box = &self.boxes[usize::from(Self::box_of(key))];
+        let &slot = lens_box.index.get(key)?;
+        lens_box.slots[slot].as_ref().map(|(_, value)| value)
+    }
+
+    /// Inserts or replaces the lens for `key`, returning the replaced value.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let lens_box = &mut self.boxes[usize::from(Self::box_of(&key))];
+        if let Some(&slot) = lens_box.index.get(&key) {
+            let (_, prev_value) = lens_box.slots[slot].as_mut().unwrap();
+            return Some(std::mem::replace(prev_value, value));
+        }
+
+        lens_box.index.insert(key.clone(), lens_box.slots.len());
+        lens_box.slots.push(Some((key, value)));
+        self.len += 1;
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let lens_box = &mut self.boxes[usize::from(Self::box_of(key))];
+        let slot = lens_box.index.remove(key)?;
+        let (_, value) = lens_box.slots[slot].take().unwrap();
+        if lens_box.slots.len() > 2 * lens_box.index.len() + 8 {
+            lens_box.compact();
+        }
+
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// The lenses of box `box_index` in order.
+    pub fn iter_box(&self, box_index: u8) -> impl Iterator<Item = (&K, &V)> {
+        self.boxes[usize::from(box_index)]
+            .slots
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+
+    /// All lenses as `(box, key, value)`, by box and then in order within each box.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &K, &V)> {
+        (0..=u8::MAX).flat_map(move |box_index| {
+            self.iter_box(box_index)
+                .map(move |(key, value)| (box_index, key, value))
+        })
+    }
+}
+
+impl<K: AsRef<[u8]> + Hash + Eq + Clone, V: Copy + Into<u64>> LensMap<K, V> {
+    /// The sum over all lenses of (box + 1) × slot × value, with slots numbered from 1.
+    pub fn focusing_power(&self) -> u64 {
+        (0..=u8::MAX)
+            .map(|box_index| {
+                let box_power: u64 = self
+                    .iter_box(box_index)
+                    .zip(1..)
+                    .map(|((_, &value), slot)| slot * value.into())
+                    .sum();
+                (u64::from(box_index) + 1) * box_power
+            })
+            .sum()
+    }
+}
+
+/// Lists the non-empty boxes like the puzzle's walkthrough, one per line.
+impl<K: AsRef<[u8]> + Hash + Eq + Clone + Display, V: Display> Display for LensMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for box_index in 0..=u8::MAX {
+            let mut lenses = self.iter_box(box_index).peekable();
+            if lenses.peek().is_none() {
+                continue;
+            }
+
+            write!(f, "Box {box_index}:")?;
+            for (key, value) in lenses {
+                write!(f, " [{key} {value}]")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(steps: &str) -> LensMap<&str, u8> {
+        let mut lens_map = LensMap::default();
+        for step in steps.split(',') {
+            if let Some(label) = step.strip_suffix('-') {
+                lens_map.remove(&label);
+            } else {
+                let (label, focal_length) = step.split_once('=').unwrap();
+                lens_map.insert(label, focal_length.parse().unwrap());
+            }
+        }
+        lens_map
+    }
+
+    fn box_contents<'a>(lens_map: &LensMap<&'a str, u8>, box_index: u8) -> Vec<(&'a str, u8)> {
+        lens_map
+            .iter_box(box_index)
+            .map(|(&key, &value)| (key, value))
+            .collect()
+    }
+
+    #[test]
+    fn example() {
+        let lens_map = run("rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7");
+        assert_eq!(hash(b"HASH"), 52);
+        assert_eq!(lens_map.len(), 5);
+        assert_eq!(lens_map.focusing_power(), 145);
+        assert_eq!(
+            lens_map.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n"
+        );
+    }
+
+    #[test]
+    fn replace_keeps_position() {
+        let mut lens_map = run("ot=9,ab=5,pc=6");
+        assert_eq!(lens_map.insert("ab", 1), Some(5));
+        assert_eq!(
+            box_contents(&lens_map, 3),
+            [("ot", 9), ("ab", 1), ("pc", 6)]
+        );
+        assert_eq!(lens_map.len(), 3);
+    }
+
+    #[test]
+    fn reinsert_appends() {
+        let mut lens_map = run("ot=9,ab=5,pc=6");
+        assert_eq!(lens_map.remove(&"ot"), Some(9));
+        assert_eq!(lens_map.remove(&"ot"), None);
+        assert_eq!(lens_map.get(&"ot"), None);
+        assert_eq!(lens_map.insert("ot", 7), None);
+        assert_eq!(
+            box_contents(&lens_map, 3),
+            [("ab", 5), ("pc", 6), ("ot", 7)]
+        );
+    }
+
+    #[test]
+    fn compaction_keeps_index() {
+        let keys: Vec<String> = (0..)
+            .map(|i| format!("k{i}"))
+            .filter(|key| hash(key.as_bytes()) == 0)
+            .take(40)
+            .collect();
+        let mut lens_map = LensMap::default();
+        for (i, key) in keys.iter().enumerate() {
+            lens_map.insert(key.as_str(), i as u8);
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if i % 4 != 3 {
+                lens_map.remove(&key.as_str());
+            }
+        }
+
+        let remaining = (0..keys.len()).filter(|i| i % 4 == 3).collect::<Vec<_>>();
+        assert!(lens_map.boxes[0].slots.len() < keys.len());
+        assert_eq!(lens_map.len(), remaining.len());
+        for &i in &remaining {
+            assert_eq!(lens_map.get(&keys[i].as_str()), Some(&(i as u8)));
+            assert_eq!(
+                lens_map.insert(keys[i].as_str(), 100 + i as u8),
+                Some(i as u8)
+            );
+        }
+        let expected: Vec<_> = remaining
+            .iter()
+            .map(|&i| (keys[i].as_str(), 100 + i as u8))
+            .collect();
+        assert_eq!(box_contents(&lens_map, 0), expected);
+    }
+
+    #[test]
+    fn iter_order() {
+        let lens_map = run("ot=9,rn=1,ab=5,cm=2");
+        let lenses: Vec<_> = lens_map
+            .iter()
+            .map(|(box_index, &key, &value)| (box_index, key, value))
+            .collect();
+        assert_eq!(
+            lenses,
+            [(0, "rn", 1), (0, "cm", 2), (3, "ot", 9), (3, "ab", 5)]
+        );
+    }
+}
@@ -1,3 +1,7 @@
+pub mod lens_map;
+pub mod log;
+pub mod piecewise_map;
+
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
@@ -0,0 +1,84 @@
+//! Verbosity for intermediate dumps, set by repeated `-v` flags or the `AOC_LOG` environment
+//! variable (`off`, `info`, `debug`, `trace` or `0`-`3`), whichever is higher.
+
+use std::{env, str::FromStr, sync::OnceLock};
+
+use anyhow::bail;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Off,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn from_count(count: usize) -> Self {
+        match count {
+            0 => Self::Off,
+            1 => Self::Info,
+            2 => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "" | "0" | "off" => Ok(Self::Off),
+            "1" | "info" => Ok(Self::Info),
+            "2" | "debug" => Ok(Self::Debug),
+            "3" | "trace" => Ok(Self::Trace),
+            _ => bail!("invalid log level: {}", s),
+        }
+    }
+}
+
+/// The number of `v`s in a flag like `-vv`.
+fn verbosity_flag(arg: &str) -> Option<usize> {
+    arg.strip_prefix('-')
+        .filter(|flag| !flag.is_empty() && flag.bytes().all(|b| b == b'v'))
+        .map(str::len)
+}
+
+pub fn level() -> Level {
+    static LEVEL: OnceLock<Level> = OnceLock::new();
+    *LEVEL.get_or_init(|| {
+        let count = env::args()
+            .skip(1)
+            .filter_map(|arg| verbosity_flag(&arg))
+            .sum();
+        let from_env = env::var("AOC_LOG").map_or(Level::Off, |value| {
+            value.parse().unwrap_or_else(|err| {
+                eprintln!("{err}");
+                Level::Off
+            })
+        });
+        from_env.max(Level::from_count(count))
+    })
+}
+
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && self::level() >= level
+}
+
+/// The command line arguments without the program name and verbosity flags.
+pub fn args() -> impl Iterator<Item = String> {
+    env::args()
+        .skip(1)
+        .filter(|arg| verbosity_flag(arg).is_none())
+}
+
+/// Prints to stderr if the given [`Level`] is enabled, without evaluating the arguments otherwise.
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::$level) {
+            eprintln!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,272 @@
+use std::ops::Range;
+
+use anyhow::{ensure, Context, Result};
+use itertools::Itertools;
+
+/// A map on `0..u64::MAX` that shifts each of a set of disjoint ranges by its own offset.
+///
+/// Stored as `(src_start, dest_start)` pairs sorted by `src_start`, where each piece extends to the
+/// start of the next one. The first piece always starts at 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiecewiseMap(Vec<(u64, u64)>);
+
+impl Default for PiecewiseMap {
+    fn default() -> Self {
+        Self(vec![(0, 0)])
+    }
+}
+
+impl PiecewiseMap {
+    /// Builds a map from `(src_start, dest_start, width)` ranges, mapping all other values to
+    /// themselves.
+    pub fn from_ranges(mut ranges: Vec<(u64, u64, u64)>) -> Result<Self> {
+        ranges.sort_unstable_by_key(|(src_start, _, _)| *src_start);
+        let mut low = 0;
+        let mut pieces = Vec::with_capacity(2 * ranges.len() + 1);
+        for (src_start, dest_start, width) in ranges {
+            ensure!(low <= src_start, "overlapping ranges at {}", src_start);
+            src_start
+                .checked_add(width)
+                .zip(dest_start.checked_add(width))
+                .context("range out of bounds")?;
+            if low < src_start {
+                pieces.push((low, low));
+            }
+            pieces.push((src_start, dest_start));
+            low = src_start + width;
+        }
+        pieces.push((low, low));
+
+        Ok(Self::from_pieces(pieces))
+    }
+
+    /// Merges consecutive pieces with the same offset.
+    fn from_pieces(pieces: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        let mut merged: Vec<(u64, u64)> = Vec::new();
+        for (src_start, dest_start) in pieces {
+            if merged
+                .last()
+                .is_some_and(|&(prev_src, _)| prev_src == src_start)
+            {
+                merged.pop();
+            }
+            let continues = merged.last().is_some_and(|&(prev_src, prev_dest)| {
+                dest_start.checked_sub(prev_dest) == Some(src_start - prev_src)
+            });
+            if !continues {
+                merged.push((src_start, dest_start));
+            }
+        }
+
+        Self(merged)
+    }
+
+    /// The source range and destination start of each piece.
+    pub fn pieces(&self) -> impl Iterator<Item = (Range<u64>, u64)> + '_ {
+        let ends = self.0.iter().skip(1).map(|&(src_start, _)| src_start);
+        self.0
+            .iter()
+            .zip(ends.chain([u64::MAX]))
+            .map(|(&(src_start, dest_start), end)| (src_start..end, dest_start))
+    }
+
+    pub fn get(&self, value: u64) -> u64 {
+        let index = self.0.partition_point(|&(src_start, _)| src_start <= value) - 1;
+        let (src_start, dest_start) = self.0[index];
+        value - src_start + dest_start
+    }
+
+    /// The image of `range`, as one range per piece it overlaps, in order of the source values.
+    pub fn image(&self, range: Range<u64>) -> impl Iterator<Item = Range<u64>> + '_ {
+        let first = self
+            .0
+            .partition_point(|&(src_start, _)| src_start <= range.start);
+        self.pieces()
+            .skip(first - 1)
+            .take_while(move |(src, _)| src.start < range.end)
+            .filter_map(move |(src, dest_start)| {
+                let low = range.start.max(src.start);
+                let high = range.end.min(src.end);
+                (low < high)
+                    .then(|| (low - src.start + dest_start)..(high - src.start + dest_start))
+            })
+    }
+
+    /// All values mapped into `range`, as sorted and merged ranges.
+    pub fn preimage(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut preimage: Vec<Range<u64>> = Vec::new();
+        for (src, dest_start) in self.pieces() {
+            let dest_end = dest_start + (src.end - src.start);
+            let low = range.start.max(dest_start);
+            let high = range.end.min(dest_end);
+            if low >= high {
+                continue;
+            }
+
+            let (low, high) = (low - dest_start + src.start, high - dest_start + src.start);
+            match preimage.last_mut() {
+                Some(last) if last.end == low => last.end = high,
+                _ => preimage.push(low..high),
+            }
+        }
+
+        preimage
+    }
+
+    /// The map applying `self` first and `next` afterwards.
+    pub fn compose(&self, next: &Self) -> Self {
+        Self::from_pieces(self.pieces().flat_map(|(src, dest_start)| {
+            let dest = dest_start..dest_start + (src.end - src.start);
+            let first = next.0.partition_point(|&(start, _)| start <= dest.start);
+            next.pieces()
+                .skip(first - 1)
+                .take_while(move |(next_src, _)| next_src.start < dest.end)
+                .map(move |(next_src, next_dest)| {
+                    let low = dest.start.max(next_src.start);
+                    (
+                        low - dest_start + src.start,
+                        low - next_src.start + next_dest,
+                    )
+                })
+        }))
+    }
+
+    /// The inverse map, if `self` is a bijection.
+    pub fn invert(&self) -> Option<Self> {
+        let pieces = self
+            .pieces()
+            .map(|(src, dest_start)| (dest_start, src.start, src.end - src.start))
+            .sorted_unstable()
+            .collect_vec();
+        let mut next = 0;
+        for &(dest_start, _, width) in &pieces {
+            if dest_start != next {
+                return None;
+            }
+            next = dest_start.checked_add(width)?;
+        }
+
+        (next == u64::MAX).then(|| {
+            Self::from_pieces(
+                pieces
+                    .into_iter()
+                    .map(|(dest_start, src_start, _)| (dest_start, src_start)),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a map from `(dest_start, src_start, width)` lines, as written in the day 5 almanac.
+    fn map(lines: &[(u64, u64, u64)]) -> PiecewiseMap {
+        let ranges = lines
+            .iter()
+            .map(|&(dest_start, src_start, width)| (src_start, dest_start, width))
+            .collect();
+        PiecewiseMap::from_ranges(ranges).unwrap()
+    }
+
+    fn almanac() -> Vec<PiecewiseMap> {
+        vec![
+            map(&[(50, 98, 2), (52, 50, 48)]),
+            map(&[(0, 15, 37), (37, 52, 2), (39, 0, 15)]),
+            map(&[(49, 53, 8), (0, 11, 42), (42, 0, 7), (57, 7, 4)]),
+            map(&[(88, 18, 7), (18, 25, 70)]),
+            map(&[(45, 77, 23), (81, 45, 19), (68, 64, 13)]),
+            map(&[(0, 69, 1), (1, 0, 69)]),
+            map(&[(60, 56, 37), (56, 93, 4)]),
+        ]
+    }
+
+    fn composed() -> PiecewiseMap {
+        almanac()
+            .iter()
+            .fold(PiecewiseMap::default(), |acc, map| acc.compose(map))
+    }
+
+    #[test]
+    fn get() {
+        let seed_to_soil = &almanac()[0];
+        let soils = [79, 14, 55, 13, 98, 99, 100].map(|seed| seed_to_soil.get(seed));
+        assert_eq!(soils, [81, 14, 57, 13, 50, 51, 100]);
+    }
+
+    #[test]
+    fn compose() {
+        let almanac = almanac();
+        let seed_to_location = composed();
+        for seed in 0..200 {
+            let location = almanac.iter().fold(seed, |value, map| map.get(value));
+            assert_eq!(seed_to_location.get(seed), location, "seed {seed}");
+        }
+        assert_eq!(
+            [79, 14, 55, 13].map(|seed| seed_to_location.get(seed)),
+            [82, 43, 86, 35]
+        );
+    }
+
+    #[test]
+    fn pieces_are_merged() {
+        assert_eq!(map(&[(10, 5, 5), (15, 10, 5)]).pieces().count(), 3);
+        assert_eq!(map(&[]).pieces().collect_vec(), [(0..u64::MAX, 0)]);
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        assert!(PiecewiseMap::from_ranges(vec![(0, 10, 5), (4, 20, 5)]).is_err());
+        assert!(PiecewiseMap::from_ranges(vec![(u64::MAX - 1, 0, 2)]).is_err());
+    }
+
+    #[test]
+    fn image_and_preimage() {
+        let seed_to_location = composed();
+        let lowest = [79..93, 55..68]
+            .into_iter()
+            .flat_map(|range| seed_to_location.image(range).collect_vec())
+            .map(|range| range.start)
+            .min();
+        assert_eq!(lowest, Some(46));
+
+        let seeds = seed_to_location.preimage(46..47);
+        assert_eq!(
+            seeds
+                .iter()
+                .map(|range| (range.start, range.end))
+                .collect_vec(),
+            [(82, 83)]
+        );
+        for range in seed_to_location.preimage(40..60) {
+            assert!(range
+                .clone()
+                .all(|seed| (40..60).contains(&seed_to_location.get(seed))));
+        }
+        let count: u64 = seed_to_location
+            .preimage(40..60)
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum();
+        assert_eq!(count, 20);
+    }
+
+    #[test]
+    fn invert() {
+        let swap = map(&[(10, 0, 5), (0, 10, 5)]);
+        let inverse = swap.invert().unwrap();
+        assert_eq!(inverse, swap);
+        for value in 0..20 {
+            assert_eq!(inverse.get(swap.get(value)), value);
+        }
+
+        let seed_to_location = composed();
+        let location_to_seed = seed_to_location.invert().unwrap();
+        for seed in 0..200 {
+            assert_eq!(location_to_seed.get(seed_to_location.get(seed)), seed);
+        }
+        assert_eq!(location_to_seed.invert(), Some(seed_to_location));
+
+        assert_eq!(map(&[(10, 0, 5)]).invert(), None);
+    }
+}